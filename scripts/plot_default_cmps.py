@@ -68,3 +68,30 @@ plt.title("Porównanie czasu wykonania metod rozwiązywania układów równań l
 plt.grid()
 plt.legend()
 plt.savefig("img/default_sparse_time.png")
+
+n_b = []
+thomas_b = []
+band_lu_b = []
+
+with open("dump/default_banded_time.csv", "r") as f:
+    reader = csv.reader(f, delimiter=";")
+    next(reader)
+    for row in reader:
+        n_b.append(int(row[0]))
+        thomas_b.append(float(row[1]))
+        band_lu_b.append(float(row[2]))
+
+plt.figure()
+plt.xscale("log")
+plt.yscale("log")
+plt.plot(n_b, thomas_b, color="red",
+         linestyle="-", marker="", label="Thomas")
+plt.plot(n_b, band_lu_b, color="blue",
+         linestyle="-", marker="", label="LU pasmowe")
+plt.xlabel("Rozmiar macierzy pasmowej")
+plt.ylabel("Czas wykonania (w ms)")
+plt.title("Porównanie czasu wykonania metod rozwiązywania układów równań liniowych przy użyciu macierzy pasmowych",
+          loc="center", wrap=True)
+plt.grid()
+plt.legend()
+plt.savefig("img/default_banded_time.png")
@@ -0,0 +1,346 @@
+use std::collections::HashMap;
+
+use crate::base::*;
+use crate::lu::LuFactorization;
+use crate::preconditioner::Preconditioner;
+use crate::sparse::{self, Sparse};
+use crate::spectral;
+
+// https://en.wikipedia.org/wiki/Multigrid_method#Algebraic_multigrid_(AMG)
+//
+// Smoothed aggregation: neighbouring intersections that are strongly
+// connected are merged into one coarse unknown, the piecewise constant
+// interpolation is smoothed with one damped Jacobi step and the coarse
+// matrix is the Galerkin product PᵀAP.
+
+const STRENGTH_THRESHOLD: f64 = 0.08;
+const COARSEST_SIZE: usize = 100;
+const MAX_LEVELS: usize = 20;
+// Interpolation weights below this fraction of the largest one in their
+// row are dropped, otherwise the coarse levels of a park with many
+// long alleys fill in almost completely.
+const TRUNCATION: f64 = 0.2;
+
+#[derive(Clone, Debug)]
+struct Level {
+    rows: Vec<Vec<(usize, f64)>>,
+    // Row i holds the interpolation weights of fine unknown i.
+    interpolation: Vec<Vec<(usize, f64)>>,
+    coarse_size: usize,
+}
+
+/// Multigrid hierarchy of a Sparse matrix, used as a solver on its own
+/// or one V-cycle at a time as a preconditioner.
+#[derive(Clone, Debug)]
+pub struct Amg {
+    levels: Vec<Level>,
+    coarsest: LuFactorization,
+}
+
+impl Amg {
+    pub fn new(a: &Sparse) -> Result<Self, MatrixError> {
+        let mut levels = Vec::new();
+        let mut current = a.clone();
+
+        while levels.len() < MAX_LEVELS {
+            let n = current.size();
+            if n <= COARSEST_SIZE {
+                break;
+            }
+
+            let rows = current.rows(n);
+            let aggregates = aggregate(&rows);
+            let coarse_size = aggregates.iter().flatten().max().map_or(0, |&c| c + 1);
+            if coarse_size == 0 || coarse_size * 10 > n * 9 {
+                break;
+            }
+
+            let interpolation = smoothed_interpolation(&rows, &aggregates);
+            current = galerkin_product(&rows, &interpolation);
+            levels.push(Level {
+                rows,
+                interpolation,
+                coarse_size,
+            });
+        }
+
+        let coarsest = LuFactorization::from_sparse(&current)?;
+
+        Ok(Self { levels, coarsest })
+    }
+
+    pub fn levels(&self) -> usize {
+        self.levels.len() + 1
+    }
+
+    /// One V-cycle for Ax = b, improving x in place. Fails if b doesn't
+    /// have the size of the matrix the hierarchy was built from.
+    pub fn v_cycle(&self, b: &[f64], x: &mut [f64]) -> Result<(), MatrixError> {
+        self.cycle(0, b, x)
+    }
+
+    fn cycle(&self, depth: usize, b: &[f64], x: &mut [f64]) -> Result<(), MatrixError> {
+        let level = match self.levels.get(depth) {
+            Some(level) => level,
+            None => {
+                x.copy_from_slice(&self.coarsest.solve(b)?);
+                return Ok(());
+            }
+        };
+        if b.len() != level.rows.len() || x.len() != level.rows.len() {
+            return Err(MatrixError::SizeError);
+        }
+
+        sparse::sor_sweep(&level.rows, b, x, 1f64, false);
+
+        let mut coarse_b = vec![0f64; level.coarse_size];
+        for (i, row) in level.rows.iter().enumerate() {
+            let r = b[i] - row.iter().map(|&(j, val)| val * x[j]).sum::<f64>();
+            for &(c, weight) in &level.interpolation[i] {
+                coarse_b[c] += weight * r;
+            }
+        }
+
+        let mut coarse_x = vec![0f64; level.coarse_size];
+        self.cycle(depth + 1, &coarse_b, &mut coarse_x)?;
+        for (xi, weights) in x.iter_mut().zip(&level.interpolation) {
+            *xi += weights.iter().map(|&(c, w)| w * coarse_x[c]).sum::<f64>();
+        }
+
+        sparse::sor_sweep(&level.rows, b, x, 1f64, true);
+        Ok(())
+    }
+
+    pub fn solve(
+        &self,
+        a: &Sparse,
+        b: &[f64],
+        x0: &[f64],
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport, MatrixError> {
+        let mut x = x0.to_vec();
+        let mut history = Vec::new();
+        let mut iterations = 0;
+
+        loop {
+            let ax = a.multiply_by_vec(&x);
+            history.push(residual_norm(b, &ax));
+            if history[iterations] < eps || iterations == max_iter {
+                return SolveReport::new(b, &ax, x, iterations, history[iterations] < eps)
+                    .with_history(history)
+                    .into_result();
+            }
+
+            self.v_cycle(b, &mut x)?;
+            iterations += 1;
+        }
+    }
+}
+
+/// One V-cycle started from zero. Panics on a residual of another size
+/// than the matrix, like the other preconditioners.
+impl Preconditioner for Amg {
+    fn apply(&self, r: &[f64]) -> Vec<f64> {
+        let mut x = vec![0f64; r.len()];
+        self.v_cycle(r, &mut x)
+            .expect("residual size doesn't match the hierarchy");
+        x
+    }
+}
+
+// Assigns every unknown to an aggregate, or to none when it has no strong
+// connections at all. Those are the exits and wells, whose identity rows
+// the smoother already solves exactly.
+fn aggregate(rows: &[Vec<(usize, f64)>]) -> Vec<Option<usize>> {
+    let n = rows.len();
+    let diagonal: Vec<f64> = rows
+        .iter()
+        .enumerate()
+        .map(|(i, row)| {
+            row.iter()
+                .find(|&&(j, _)| j == i)
+                .map_or(0f64, |&(_, val)| val.abs())
+        })
+        .collect();
+
+    let mut strong: Vec<Vec<(usize, f64)>> = vec![Vec::new(); n];
+    for (i, row) in rows.iter().enumerate() {
+        for &(j, val) in row {
+            let strength = val.abs() / (diagonal[i] * diagonal[j]).sqrt();
+            if j != i && strength >= STRENGTH_THRESHOLD {
+                strong[i].push((j, strength));
+                strong[j].push((i, strength));
+            }
+        }
+    }
+
+    let mut aggregates: Vec<Option<usize>> = vec![None; n];
+    let mut count = 0;
+
+    // Pass 1: unknowns whose whole strong neighbourhood is still free.
+    for i in 0..n {
+        if strong[i].is_empty() || aggregates[i].is_some() {
+            continue;
+        }
+        if strong[i].iter().all(|&(j, _)| aggregates[j].is_none()) {
+            aggregates[i] = Some(count);
+            for &(j, _) in &strong[i] {
+                aggregates[j] = Some(count);
+            }
+            count += 1;
+        }
+    }
+
+    // Pass 2: join the aggregate of the strongest aggregated neighbour.
+    let first_pass = aggregates.clone();
+    for i in 0..n {
+        if aggregates[i].is_some() {
+            continue;
+        }
+        aggregates[i] = strong[i]
+            .iter()
+            .filter(|&&(j, _)| first_pass[j].is_some())
+            .max_by(|p, q| p.1.total_cmp(&q.1))
+            .and_then(|&(j, _)| first_pass[j]);
+    }
+
+    // Pass 3: whatever is left forms aggregates of its own.
+    for i in 0..n {
+        if strong[i].is_empty() || aggregates[i].is_some() {
+            continue;
+        }
+        aggregates[i] = Some(count);
+        for &(j, _) in &strong[i] {
+            if aggregates[j].is_none() {
+                aggregates[j] = Some(count);
+            }
+        }
+        count += 1;
+    }
+
+    aggregates
+}
+
+// P = (I - ω D⁻¹A) P_tent, with ω = 4 / (3 ρ(D⁻¹A)).
+fn smoothed_interpolation(
+    rows: &[Vec<(usize, f64)>],
+    aggregates: &[Option<usize>],
+) -> Vec<Vec<(usize, f64)>> {
+    let diagonal: Vec<f64> = rows
+        .iter()
+        .enumerate()
+        .map(|(i, row)| {
+            row.iter()
+                .find(|&&(j, _)| j == i)
+                .map_or(1f64, |&(_, val)| val)
+        })
+        .collect();
+
+    let apply = |v: &[f64]| {
+        rows.iter()
+            .zip(&diagonal)
+            .map(|(row, d)| row.iter().map(|&(j, val)| val * v[j]).sum::<f64>() / d)
+            .collect()
+    };
+    let omega = 4f64 / (3f64 * spectral::power_radius(apply, rows.len(), 20).max(1f64));
+
+    rows.iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let mut weights: HashMap<usize, f64> = HashMap::new();
+            if let Some(c) = aggregates[i] {
+                *weights.entry(c).or_insert(0f64) += 1f64;
+            }
+            for &(j, val) in row {
+                if let Some(c) = aggregates[j] {
+                    *weights.entry(c).or_insert(0f64) -= omega * val / diagonal[i];
+                }
+            }
+            truncate(weights.into_iter().collect())
+        })
+        .collect()
+}
+
+// Drops the small weights of an interpolation row and rescales the rest,
+// so the row still sums to the same value.
+fn truncate(weights: Vec<(usize, f64)>) -> Vec<(usize, f64)> {
+    let largest = weights.iter().fold(0f64, |max, &(_, w)| max.max(w.abs()));
+    let total: f64 = weights.iter().map(|&(_, w)| w).sum();
+
+    let kept: Vec<(usize, f64)> = weights
+        .into_iter()
+        .filter(|&(_, w)| w != 0f64 && w.abs() >= TRUNCATION * largest)
+        .collect();
+    let kept_total: f64 = kept.iter().map(|&(_, w)| w).sum();
+    let scale = if kept_total != 0f64 {
+        total / kept_total
+    } else {
+        1f64
+    };
+
+    kept.into_iter().map(|(c, w)| (c, w * scale)).collect()
+}
+
+// PᵀAP, row by row of AP.
+fn galerkin_product(rows: &[Vec<(usize, f64)>], interpolation: &[Vec<(usize, f64)>]) -> Sparse {
+    let mut coarse: HashMap<(usize, usize), f64> = HashMap::new();
+
+    for (i, row) in rows.iter().enumerate() {
+        let mut ap_row: HashMap<usize, f64> = HashMap::new();
+        for &(j, val) in row {
+            for &(c, weight) in &interpolation[j] {
+                *ap_row.entry(c).or_insert(0f64) += val * weight;
+            }
+        }
+
+        for &(r, weight) in &interpolation[i] {
+            for (&c, &val) in &ap_row {
+                *coarse.entry((r, c)).or_insert(0f64) += weight * val;
+            }
+        }
+    }
+
+    let mut out = Sparse::from_size(coarse.len());
+    for ((r, c), val) in coarse {
+        out.set_value(r, c, val);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comparisons::compare_vecs;
+    use crate::fixtures::grid_park;
+
+    #[test]
+    fn test_amg_default_path() {
+        let n = 500;
+        let a = Sparse::init_default_path(n);
+        let mut b = vec![0f64; n];
+        b[0] = 1f64;
+        let expected: Vec<f64> = (0..n).map(|i| 1.0 - i as f64 / (n - 1) as f64).collect();
+
+        let amg = Amg::new(&a).unwrap();
+        assert!(amg.levels() > 1);
+        let report = amg.solve(&a, &b, &vec![0f64; n], 1e-12, 100).unwrap();
+        assert!(compare_vecs(&expected, &report.solution, 1e-10));
+    }
+
+    #[test]
+    fn test_amg_park() {
+        let cfg = grid_park(24);
+        let (sparse, b) = Sparse::from_config(&cfg);
+        let (symmetric, symmetric_b) = Sparse::from_config_symmetric(&cfg);
+        let expected = sparse.gaussian_partial_pivot(&b).unwrap();
+        let x0 = vec![0f64; b.len()];
+
+        for (a, b) in [(&sparse, &b), (&symmetric, &symmetric_b)] {
+            let amg = Amg::new(a).unwrap();
+            assert!(amg.levels() > 1);
+            let report = amg.solve(a, b, &x0, 1e-12, 200).unwrap();
+            assert!(compare_vecs(&expected, &report.solution, 1e-10));
+        }
+    }
+}
@@ -0,0 +1,380 @@
+use crate::base::*;
+use crate::matrix::Matrix;
+use crate::sparse::Sparse;
+use crate::Config;
+
+// https://en.wikipedia.org/wiki/Band_matrix
+
+/// Square matrix with nonzero entries only on the diagonal, the `lower`
+/// subdiagonals and the `upper` superdiagonals. Paths and chains of
+/// intersections are tridiagonal, lower = upper = 1.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Banded {
+    lower: usize,
+    upper: usize,
+    // Row i holds columns i - lower ..= i + upper, the ones that fall
+    // outside the matrix are kept at zero.
+    bands: Vec<Vec<f64>>,
+}
+
+/// Band LU factorization with partial pivoting. Row swaps widen U to
+/// lower + upper superdiagonals, L keeps its lower subdiagonals.
+#[derive(Clone, Debug)]
+pub struct BandLu {
+    // Row swapped with row k at step k, as in LAPACK's gbtrf.
+    pivots: Vec<usize>,
+    // Multipliers of step k for rows k + 1 ..= k + lower.
+    multipliers: Vec<Vec<f64>>,
+    // Row k of U starting at the diagonal.
+    upper: Vec<Vec<f64>>,
+}
+
+impl Banded {
+    pub fn from_size(size: usize, lower: usize, upper: usize) -> Self {
+        Self {
+            lower,
+            upper,
+            bands: vec![vec![0f64; lower + upper + 1]; size],
+        }
+    }
+
+    pub fn from_matrix(a: &Matrix) -> Self {
+        let rows = a
+            .rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .enumerate()
+                    .filter(|(_, &val)| val != 0f64)
+                    .map(|(j, &val)| (j, val))
+                    .collect()
+            })
+            .collect();
+        Self::from_rows(rows)
+    }
+
+    pub fn from_sparse(a: &Sparse) -> Self {
+        Self::from_rows(a.rows(a.size()))
+    }
+
+    fn from_rows(rows: Vec<Vec<(usize, f64)>>) -> Self {
+        let mut lower = 0;
+        let mut upper = 0;
+        for (i, row) in rows.iter().enumerate() {
+            for &(j, _) in row {
+                lower = lower.max(i.saturating_sub(j));
+                upper = upper.max(j.saturating_sub(i));
+            }
+        }
+
+        let mut out = Self::from_size(rows.len(), lower, upper);
+        for (i, row) in rows.into_iter().enumerate() {
+            for (j, val) in row {
+                out.bands[i][j + lower - i] = val;
+            }
+        }
+        out
+    }
+
+    /// Intersection system of a park that is a chain, with the unknowns in
+    /// the given order along it, see Config::chain_order.
+    pub fn from_config_chain(cfg: &Config, order: &[usize]) -> (Self, Vec<f64>) {
+        let n = order.len();
+        let mut position = vec![0; n];
+        for (k, &i) in order.iter().enumerate() {
+            position[i] = k;
+        }
+
+        let degrees = cfg.conductance_degrees::<f64>();
+        let mut out = Self::from_size(n, 1, 1);
+        let mut b = vec![0f64; n];
+        for (k, &i) in order.iter().enumerate() {
+            out.bands[k][1] = 1f64;
+            if cfg.inters[i].exit {
+                b[k] = 1f64;
+            }
+        }
+
+        for (i, j, weight) in cfg.conductances::<f64>() {
+            if cfg.inters[i].exit || cfg.inters[i].well {
+                continue;
+            }
+
+            let (k, l) = (position[i], position[j]);
+            out.bands[k][l + 1 - k] -= weight / degrees[i];
+        }
+
+        (out, b)
+    }
+
+    pub fn init_default_path(size: usize) -> Self {
+        let mut out = Self::from_size(size, 1, 1);
+
+        out.bands[0][1] = 1f64;
+        out.bands[size - 1][1] = 1f64;
+
+        for row in &mut out.bands[1..size - 1] {
+            row.copy_from_slice(&[-0.5, 1f64, -0.5]);
+        }
+
+        out
+    }
+
+    pub fn size(&self) -> usize {
+        self.bands.len()
+    }
+
+    pub fn bandwidths(&self) -> (usize, usize) {
+        (self.lower, self.upper)
+    }
+
+    pub fn get_value(&self, i: usize, j: usize) -> f64 {
+        if j + self.lower < i || j > i + self.upper {
+            return 0f64;
+        }
+        self.bands[i][j + self.lower - i]
+    }
+
+    pub fn set_value(&mut self, i: usize, j: usize, val: f64) -> Result<(), MatrixError> {
+        if i >= self.size() || j >= self.size() || j + self.lower < i || j > i + self.upper {
+            return Err(MatrixError::SizeError);
+        }
+        self.bands[i][j + self.lower - i] = val;
+        Ok(())
+    }
+
+    pub fn multiply_by_vec(&self, other: &[f64]) -> Result<Vec<f64>, MatrixError> {
+        let n = self.size();
+        if other.len() != n {
+            return Err(MatrixError::SizeError);
+        }
+
+        Ok(self
+            .bands
+            .iter()
+            .enumerate()
+            .map(|(i, row)| {
+                let first = i.saturating_sub(self.lower);
+                let last = (i + self.upper).min(n - 1);
+                (first..=last)
+                    .map(|j| row[j + self.lower - i] * other[j])
+                    .sum()
+            })
+            .collect())
+    }
+
+    // https://en.wikipedia.org/wiki/Tridiagonal_matrix_algorithm
+    //
+    // Gaussian elimination without pivoting in O(n), fine for the
+    // diagonally dominant chains but not for tridiagonal systems in general.
+    pub fn solve_tridiagonal(&self, b: &[f64]) -> Result<Vec<f64>, MatrixError> {
+        let n = self.size();
+        if self.lower != 1 || self.upper != 1 || b.len() != n {
+            return Err(MatrixError::SizeError);
+        }
+
+        let mut c = vec![0f64; n];
+        let mut d = vec![0f64; n];
+        for i in 0..n {
+            let [sub, diag, sup] = [self.bands[i][0], self.bands[i][1], self.bands[i][2]];
+            let c_prev = if i > 0 { c[i - 1] } else { 0f64 };
+            let d_prev = if i > 0 { d[i - 1] } else { 0f64 };
+
+            let pivot = diag - sub * c_prev;
+            if pivot == 0f64 {
+                return Err(MatrixError::ZeroPivotError);
+            }
+            c[i] = sup / pivot;
+            d[i] = (b[i] - sub * d_prev) / pivot;
+        }
+
+        let mut x = d;
+        for i in (0..n.saturating_sub(1)).rev() {
+            x[i] -= c[i] * x[i + 1];
+        }
+
+        Ok(x)
+    }
+
+    pub fn lu(&self) -> Result<BandLu, MatrixError> {
+        BandLu::from_banded(self)
+    }
+
+    /// Thomas algorithm for tridiagonal matrices, band LU otherwise.
+    pub fn solve(&self, b: &[f64]) -> Result<Vec<f64>, MatrixError> {
+        match self.bandwidths() {
+            (1, 1) => self.solve_tridiagonal(b),
+            _ => self.lu()?.solve(b),
+        }
+    }
+}
+
+impl BandLu {
+    pub fn from_banded(a: &Banded) -> Result<Self, MatrixError> {
+        let n = a.size();
+        let kl = a.lower;
+        let width = kl + a.upper + 1;
+
+        // Each work row is (first column, values from there on). Rows swapped
+        // up from below bring fill-in up to lower + upper past the diagonal.
+        let mut work: Vec<(usize, Vec<f64>)> = a
+            .bands
+            .iter()
+            .enumerate()
+            .map(|(i, row)| {
+                let skipped = kl.saturating_sub(i);
+                let mut values = row[skipped..].to_vec();
+                values.resize(width + kl - skipped, 0f64);
+                (i + skipped - kl, values)
+            })
+            .collect();
+
+        let mut pivots = Vec::with_capacity(n);
+        let mut multipliers = Vec::with_capacity(n);
+        let mut upper = Vec::with_capacity(n);
+
+        for k in 0..n {
+            let last_row = (k + kl).min(n - 1);
+            let max_row = (k..=last_row)
+                .max_by(|&p, &q| {
+                    let (p, q) = (&work[p], &work[q]);
+                    p.1[k - p.0].abs().total_cmp(&q.1[k - q.0].abs())
+                })
+                .unwrap_or(k);
+
+            let pivot = work[max_row].1[k - work[max_row].0];
+            if pivot == 0f64 {
+                return Err(MatrixError::Unsolvable);
+            }
+            work.swap(k, max_row);
+            pivots.push(max_row);
+
+            let last_col = (k + width - 1).min(n - 1);
+            let (top, bottom) = work.split_at_mut(k + 1);
+            let (first, values) = &top[k];
+            let mut pivot_vals = values[k - first..].to_vec();
+            pivot_vals.resize(last_col + 1 - k, 0f64);
+
+            let mut step = Vec::with_capacity(last_row - k);
+            for (first, values) in &mut bottom[..last_row - k] {
+                let factor = values[k - *first] / pivot;
+                step.push(factor);
+                // Column k is done with, so the row can start at k + 1.
+                values.drain(..=k - *first);
+                *first = k + 1;
+                if factor == 0f64 {
+                    continue;
+                }
+
+                if values.len() < last_col - k {
+                    values.resize(last_col - k, 0f64);
+                }
+                for (val, pivot_val) in values.iter_mut().zip(&pivot_vals[1..]) {
+                    *val -= factor * pivot_val;
+                }
+            }
+
+            multipliers.push(step);
+            upper.push(pivot_vals);
+        }
+
+        Ok(Self {
+            pivots,
+            multipliers,
+            upper,
+        })
+    }
+
+    pub fn size(&self) -> usize {
+        self.pivots.len()
+    }
+
+    pub fn solve(&self, b: &[f64]) -> Result<Vec<f64>, MatrixError> {
+        let n = self.size();
+        if b.len() != n {
+            return Err(MatrixError::SizeError);
+        }
+
+        let mut y = b.to_vec();
+        for k in 0..n {
+            y.swap(k, self.pivots[k]);
+            for (offset, factor) in self.multipliers[k].iter().enumerate() {
+                y[k + 1 + offset] -= factor * y[k];
+            }
+        }
+
+        for k in (0..n).rev() {
+            let row = &self.upper[k];
+            let sum: f64 = row[1..]
+                .iter()
+                .zip(&y[k + 1..])
+                .map(|(val, yj)| val * yj)
+                .sum();
+            y[k] = (y[k] - sum) / row[0];
+        }
+
+        Ok(y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comparisons::compare_vecs;
+
+    #[test]
+    fn test_thomas_default_path() {
+        let n = 1000;
+        let a = Banded::init_default_path(n);
+        assert_eq!(a, Banded::from_matrix(&Matrix::init_default_path(n)));
+
+        let mut b = vec![0f64; n];
+        b[0] = 1f64;
+        let expected: Vec<f64> = (0..n).map(|i| 1.0 - i as f64 / (n - 1) as f64).collect();
+        assert!(compare_vecs(
+            &expected,
+            &a.solve_tridiagonal(&b).unwrap(),
+            1e-12
+        ));
+        assert!(compare_vecs(
+            &expected,
+            &a.lu().unwrap().solve(&b).unwrap(),
+            1e-12
+        ));
+    }
+
+    #[test]
+    fn test_band_lu_pivoting() {
+        // Needs row swaps, and lower = 2, upper = 1.
+        let a = Matrix::from_vecs(vec![
+            vec![0.0, 2.0, 0.0, 0.0],
+            vec![1.0, 1.0, 3.0, 0.0],
+            vec![4.0, 1.0, 1.0, 1.0],
+            vec![0.0, 5.0, 2.0, 1.0],
+        ]);
+        let banded = Banded::from_matrix(&a);
+        assert_eq!(banded.bandwidths(), (2, 1));
+
+        let b = vec![1.0, 2.0, 3.0, 4.0];
+        let x = banded.solve(&b).unwrap();
+        assert!(compare_vecs(&a.multiply_by_vec(&x).unwrap(), &b, 1e-12));
+        assert!(banded.solve_tridiagonal(&b).is_err());
+    }
+
+    #[test]
+    fn test_chain_config() {
+        // Exit 1 - 3 - 4 - 2 well, listed out of order.
+        let chain = Config::build(crate::Sets(vec![
+            vec![vec![4, 3], vec![3, 4, 2], vec![1, 3, 1], vec![4, 2, 3]],
+            vec![vec![1, 2], vec![1, 1], vec![1, 4]],
+        ]));
+        assert_eq!(chain.chain_order(), Some(vec![0, 2, 3, 1]));
+
+        let (sparse, b) = Sparse::from_config(&chain);
+        let expected = sparse.gaussian_partial_pivot(&b).unwrap();
+        assert!(compare_vecs(&expected, &chain.solve().unwrap(), 1e-12));
+
+        let cfg = Config::build(crate::parse_config("default.config"));
+        assert!(cfg.chain_order().is_none());
+    }
+}
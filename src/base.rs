@@ -1,31 +1,232 @@
 use std::error::Error;
 
+use crate::preconditioner::Preconditioner;
+use crate::scalar::Scalar;
+use crate::spectral::Dominance;
+use crate::stopping::StoppingCriterion;
+
 #[derive(Debug)]
-pub enum MatrixError {
+pub enum MatrixError<T: Scalar = f64> {
     SizeError,
     ZeroPivotError,
     Unsolvable,
+    NotPositiveDefinite,
+    /// An iterative solver ran out of iterations, the report still holds
+    /// the last iterate.
+    NotConverged(SolveReport<T>),
+    /// The iteration matrix of a stationary method has spectral radius
+    /// of at least 1.
+    Divergent(f64),
 }
 
-impl Error for MatrixError {}
+impl<T: Scalar> Error for MatrixError<T> {}
 
-impl std::fmt::Display for MatrixError {
+impl<T: Scalar> std::fmt::Display for MatrixError<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::SizeError => writeln!(f, "invalid matrix size"),
             Self::ZeroPivotError => writeln!(f, "zero pivot - partial pivoting is required"),
             Self::Unsolvable => writeln!(f, "unsolvable matrix"),
+            Self::NotPositiveDefinite => writeln!(f, "matrix is not positive definite"),
+            Self::NotConverged(report) => writeln!(
+                f,
+                "no convergence after {} iterations, residual norm {:e}",
+                report.iterations, report.residual_norm
+            ),
+            Self::Divergent(rho) => writeln!(
+                f,
+                "iteration matrix has spectral radius {} >= 1 - the method diverges",
+                rho
+            ),
+        }
+    }
+}
+
+/// Outcome of an iterative solver.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SolveReport<T: Scalar = f64> {
+    pub solution: Vec<T>,
+    pub iterations: usize,
+    pub converged: bool,
+    /// ‖b - Ax‖₂ of the returned solution.
+    pub residual_norm: f64,
+    /// Residual norm at every stopping test, for the solvers whose test
+    /// looks at the residual. The stationary methods only keep it when
+    /// their StoppingCriterion is a residual test, in its norm.
+    pub residual_history: Option<Vec<f64>>,
+}
+
+impl<T: Scalar> SolveReport<T> {
+    pub fn new(b: &[T], ax: &[T], solution: Vec<T>, iterations: usize, converged: bool) -> Self {
+        Self {
+            solution,
+            iterations,
+            converged,
+            residual_norm: residual_norm(b, ax),
+            residual_history: None,
+        }
+    }
+
+    pub fn with_history(mut self, history: Vec<f64>) -> Self {
+        self.residual_history = Some(history);
+        self
+    }
+
+    /// MatrixError::NotConverged unless the solver converged.
+    pub fn into_result(self) -> Result<Self, MatrixError<T>> {
+        if self.converged {
+            Ok(self)
+        } else {
+            Err(MatrixError::NotConverged(self))
+        }
+    }
+}
+
+/// ‖b - Ax‖₂, given Ax. The differences are taken in the working
+/// precision, only the norm itself in f64.
+pub fn residual_norm<T: Scalar>(b: &[T], ax: &[T]) -> f64 {
+    b.iter()
+        .zip(ax)
+        .map(|(&bi, &axi)| (bi - axi).to_f64().powi(2))
+        .sum::<f64>()
+        .sqrt()
+}
+
+/// Relaxation factor ω of SOR and SSOR.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Relaxation {
+    Fixed(f64),
+    /// Derived from the spectral radius of the Jacobi iteration matrix.
+    Auto,
+}
+
+impl Relaxation {
+    pub fn factor<T: Scalar, M: MatrixBase<T>>(self, a: &M) -> f64 {
+        match self {
+            Self::Fixed(omega) => omega,
+            Self::Auto => crate::spectral::optimal_omega(a.jacobi_spectral_radius(10_000)),
         }
     }
 }
 
-pub trait MatrixBase {
+pub trait MatrixBase<T: Scalar = f64> {
     fn init_default_path(size: usize) -> Self;
-    fn jacobi(&self, b: &Vec<f64>, x0: &Vec<f64>, eps: f64, max_iter: usize) -> Vec<f64>;
-    fn gaussian(&self, b: &Vec<f64>) -> Result<Vec<f64>, MatrixError>;
-    fn partial_pivot(&self, b: &Vec<f64>) -> (Self, Vec<f64>)
-    where
-        Self: Sized;
-    fn gaussian_partial_pivot(&self, b: &Vec<f64>) -> Result<Vec<f64>, MatrixError>;
-    fn gauss_seidel(&self, b: &Vec<f64>, x0: &Vec<f64>, eps: f64, max_iter: usize) -> Vec<f64>;
+    fn jacobi(
+        &self,
+        b: &Vec<T>,
+        x0: &Vec<T>,
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>>;
+    fn jacobi_until(
+        &self,
+        b: &[T],
+        x0: &[T],
+        stop: &StoppingCriterion,
+    ) -> Result<SolveReport<T>, MatrixError<T>>;
+    fn gaussian(&self, b: &Vec<T>) -> Result<Vec<T>, MatrixError<T>>;
+    fn gaussian_partial_pivot(&self, b: &Vec<T>) -> Result<Vec<T>, MatrixError<T>>;
+    fn gaussian_complete_pivot(&self, b: &[T]) -> Result<Vec<T>, MatrixError<T>>;
+    fn gaussian_scaled_pivot(&self, b: &[T]) -> Result<Vec<T>, MatrixError<T>>;
+    fn gauss_seidel(
+        &self,
+        b: &Vec<T>,
+        x0: &Vec<T>,
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>>;
+    fn gauss_seidel_until(
+        &self,
+        b: &[T],
+        x0: &[T],
+        stop: &StoppingCriterion,
+    ) -> Result<SolveReport<T>, MatrixError<T>>;
+    fn conjugate_gradient(
+        &self,
+        b: &[T],
+        x0: &[T],
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>>;
+    fn gmres(
+        &self,
+        b: &[T],
+        x0: &[T],
+        restart: usize,
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>>;
+    fn bicgstab(
+        &self,
+        b: &[T],
+        x0: &[T],
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>>;
+    fn preconditioned_conjugate_gradient(
+        &self,
+        b: &[T],
+        x0: &[T],
+        precond: &dyn Preconditioner<T>,
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>>;
+    fn preconditioned_gmres(
+        &self,
+        b: &[T],
+        x0: &[T],
+        precond: &dyn Preconditioner<T>,
+        restart: usize,
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>>;
+    fn preconditioned_bicgstab(
+        &self,
+        b: &[T],
+        x0: &[T],
+        precond: &dyn Preconditioner<T>,
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>>;
+    fn richardson(
+        &self,
+        b: &[T],
+        x0: &[T],
+        precond: &dyn Preconditioner<T>,
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>>;
+    fn jacobi_spectral_radius(&self, max_iter: usize) -> f64;
+    fn gauss_seidel_spectral_radius(&self, max_iter: usize) -> f64;
+    fn diagonal_dominance(&self) -> Dominance;
+    fn sor(
+        &self,
+        b: &[T],
+        x0: &[T],
+        omega: Relaxation,
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>>;
+    fn sor_until(
+        &self,
+        b: &[T],
+        x0: &[T],
+        omega: Relaxation,
+        stop: &StoppingCriterion,
+    ) -> Result<SolveReport<T>, MatrixError<T>>;
+    fn ssor(
+        &self,
+        b: &[T],
+        x0: &[T],
+        omega: Relaxation,
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>>;
+    fn ssor_until(
+        &self,
+        b: &[T],
+        x0: &[T],
+        omega: Relaxation,
+        stop: &StoppingCriterion,
+    ) -> Result<SolveReport<T>, MatrixError<T>>;
 }
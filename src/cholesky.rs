@@ -0,0 +1,233 @@
+use std::collections::BTreeMap;
+
+use crate::base::*;
+use crate::matrix::Matrix;
+use crate::sparse::Sparse;
+
+/// LDLᵀ factorization of a symmetric positive definite matrix, such as
+/// the one from Matrix::reduced_laplacian. Only the lower triangle of
+/// the matrix is ever read.
+#[derive(Clone, Debug)]
+pub struct Ldlt {
+    // Column k of the unit lower triangular L below the diagonal,
+    // as (row, value) pairs.
+    lower: Vec<Vec<(usize, f64)>>,
+    diagonal: Vec<f64>,
+}
+
+/// Cholesky factorization A = LLᵀ, derived from the LDLᵀ one.
+#[derive(Clone, Debug)]
+pub struct Cholesky {
+    // Column k of L, diagonal entry first, as (row, value) pairs.
+    columns: Vec<Vec<(usize, f64)>>,
+}
+
+impl Ldlt {
+    pub fn from_matrix(a: &Matrix) -> Result<Self, MatrixError> {
+        let n = a.rows.len();
+        if a.rows.iter().any(|row| row.len() != n) {
+            return Err(MatrixError::SizeError);
+        }
+
+        // Right-looking elimination on the lower triangle, skipping the
+        // rows that have nothing to eliminate in the current column.
+        let mut w = a.rows.clone();
+        let mut lower = Vec::with_capacity(n);
+        let mut diagonal = Vec::with_capacity(n);
+
+        for k in 0..n {
+            let d = w[k][k];
+            if d <= 0f64 || d.is_nan() {
+                return Err(MatrixError::NotPositiveDefinite);
+            }
+
+            let col: Vec<f64> = w.iter().map(|row| row[k] / d).collect();
+            for (i, row) in w.iter_mut().enumerate().skip(k + 1) {
+                if col[i] == 0f64 {
+                    continue;
+                }
+                for (val, l_jk) in row[k + 1..=i].iter_mut().zip(&col[k + 1..=i]) {
+                    *val -= col[i] * l_jk * d;
+                }
+            }
+
+            lower.push(
+                ((k + 1)..n)
+                    .filter(|&i| col[i] != 0f64)
+                    .map(|i| (i, col[i]))
+                    .collect(),
+            );
+            diagonal.push(d);
+        }
+
+        Ok(Self { lower, diagonal })
+    }
+
+    /// Right-looking elimination on the columns of the lower triangle,
+    /// so only the entries of the park graph and their fill-in are stored.
+    pub fn from_sparse(a: &Sparse) -> Result<Self, MatrixError> {
+        let n = a.size();
+        let mut cols: Vec<BTreeMap<usize, f64>> = vec![BTreeMap::new(); n];
+        for (i, row) in a.rows(n).into_iter().enumerate() {
+            for (j, val) in row {
+                if j <= i {
+                    cols[j].insert(i, val);
+                }
+            }
+        }
+
+        let mut lower = Vec::with_capacity(n);
+        let mut diagonal = Vec::with_capacity(n);
+
+        for k in 0..n {
+            let mut col = std::mem::take(&mut cols[k]);
+            let d = col.remove(&k).unwrap_or(0f64);
+            if d <= 0f64 || d.is_nan() {
+                return Err(MatrixError::NotPositiveDefinite);
+            }
+
+            let below: Vec<(usize, f64)> = col.into_iter().map(|(i, val)| (i, val / d)).collect();
+            for (idx, &(i, l_ik)) in below.iter().enumerate() {
+                for &(j, l_jk) in &below[..=idx] {
+                    *cols[j].entry(i).or_insert(0f64) -= l_ik * l_jk * d;
+                }
+            }
+
+            lower.push(below);
+            diagonal.push(d);
+        }
+
+        Ok(Self { lower, diagonal })
+    }
+
+    pub fn size(&self) -> usize {
+        self.diagonal.len()
+    }
+
+    pub fn diagonal(&self) -> &[f64] {
+        &self.diagonal
+    }
+
+    pub fn solve(&self, b: &[f64]) -> Result<Vec<f64>, MatrixError> {
+        if b.len() != self.size() {
+            return Err(MatrixError::SizeError);
+        }
+
+        let mut x = b.to_vec();
+        for k in 0..x.len() {
+            for &(i, val) in &self.lower[k] {
+                x[i] -= val * x[k];
+            }
+        }
+        for (xk, d) in x.iter_mut().zip(&self.diagonal) {
+            *xk /= d;
+        }
+        for k in (0..x.len()).rev() {
+            for &(i, val) in &self.lower[k] {
+                x[k] -= val * x[i];
+            }
+        }
+
+        Ok(x)
+    }
+}
+
+impl Cholesky {
+    pub fn from_matrix(a: &Matrix) -> Result<Self, MatrixError> {
+        Ok(Self::from_ldlt(Ldlt::from_matrix(a)?))
+    }
+
+    pub fn from_sparse(a: &Sparse) -> Result<Self, MatrixError> {
+        Ok(Self::from_ldlt(Ldlt::from_sparse(a)?))
+    }
+
+    fn from_ldlt(ldlt: Ldlt) -> Self {
+        let columns = ldlt
+            .lower
+            .into_iter()
+            .zip(ldlt.diagonal)
+            .enumerate()
+            .map(|(k, (below, d))| {
+                let root = d.sqrt();
+                std::iter::once((k, root))
+                    .chain(below.into_iter().map(|(i, val)| (i, val * root)))
+                    .collect()
+            })
+            .collect();
+
+        Self { columns }
+    }
+
+    pub fn size(&self) -> usize {
+        self.columns.len()
+    }
+
+    pub fn solve(&self, b: &[f64]) -> Result<Vec<f64>, MatrixError> {
+        if b.len() != self.size() {
+            return Err(MatrixError::SizeError);
+        }
+
+        let mut x = b.to_vec();
+        for k in 0..x.len() {
+            x[k] /= self.columns[k][0].1;
+            for &(i, val) in &self.columns[k][1..] {
+                x[i] -= val * x[k];
+            }
+        }
+        for k in (0..x.len()).rev() {
+            for &(i, val) in &self.columns[k][1..] {
+                x[k] -= val * x[i];
+            }
+            x[k] /= self.columns[k][0].1;
+        }
+
+        Ok(x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comparisons::compare_vecs;
+    use crate::Config;
+
+    #[test]
+    fn test_cholesky_ldlt() {
+        let a = Matrix::from_vecs(vec![
+            vec![4.0, -1.0, 0.0],
+            vec![-1.0, 4.0, -2.0],
+            vec![0.0, -2.0, 3.0],
+        ]);
+        let b = vec![1.0, 2.0, 3.0];
+        let expected = a.gaussian_partial_pivot(&b).unwrap();
+
+        let cholesky = Cholesky::from_matrix(&a).unwrap().solve(&b).unwrap();
+        assert!(compare_vecs(&expected, &cholesky, 1e-12));
+        let ldlt = Ldlt::from_sparse(&Sparse::from_vecs(a.rows.clone())).unwrap();
+        assert!(compare_vecs(&expected, &ldlt.solve(&b).unwrap(), 1e-12));
+
+        let indefinite = Matrix::from_vecs(vec![vec![1.0, 2.0], vec![2.0, 1.0]]);
+        assert!(Cholesky::from_matrix(&indefinite).is_err());
+    }
+
+    #[test]
+    fn test_reduced_laplacian() {
+        let cfg = Config::build(crate::parse_config("default.config"));
+        let (a, b) = Matrix::from_config(&cfg);
+        let expected = a.gaussian_partial_pivot(&b).unwrap();
+
+        let (reduced, reduced_b) = Matrix::reduced_laplacian(&cfg);
+        let x = Ldlt::from_matrix(&reduced)
+            .unwrap()
+            .solve(&reduced_b)
+            .unwrap();
+        assert!(compare_vecs(&expected, &cfg.expand_interior(&x), 1e-12));
+
+        let (reduced, reduced_b) = Sparse::reduced_laplacian(&cfg);
+        let x = Cholesky::from_sparse(&reduced)
+            .unwrap()
+            .solve(&reduced_b)
+            .unwrap();
+        assert!(compare_vecs(&expected, &cfg.expand_interior(&x), 1e-12));
+    }
+}
@@ -0,0 +1,237 @@
+use std::collections::VecDeque;
+
+use rayon::prelude::*;
+
+use crate::base::*;
+use crate::parallel::{self, MIN_ROWS_PER_TASK};
+use crate::scalar::Scalar;
+use crate::sparse::Sparse;
+use crate::stopping::{Monitor, StoppingCriterion, StoppingTest};
+use crate::Config;
+
+// https://en.wikipedia.org/wiki/Graph_coloring#Greedy_coloring
+//
+// Row i of the park's system only couples x[i] to the neighbours of i, so
+// intersections of the same color never depend on each other and a
+// Gauss-Seidel sweep can update a whole color at once. The sweep is the
+// sequential one on the intersections reordered color by color, so the
+// result doesn't depend on the number of threads.
+
+impl Config {
+    /// Intersections grouped by color, no two neighbours share one. Parks
+    /// without odd cycles get the red-black ordering with two colors,
+    /// the others a greedy coloring, largest degree first.
+    pub fn coloring(&self) -> Vec<Vec<usize>> {
+        let adjacency = self.adjacency();
+        let colors = red_black(&adjacency).unwrap_or_else(|| greedy(&adjacency));
+
+        let mut classes = vec![Vec::new(); colors.iter().max().map_or(0, |&c| c + 1)];
+        for (i, &c) in colors.iter().enumerate() {
+            classes[c].push(i);
+        }
+        classes
+    }
+}
+
+// Two colors by breadth-first search, None if some cycle is odd.
+fn red_black(adjacency: &[Vec<usize>]) -> Option<Vec<usize>> {
+    let mut colors = vec![None; adjacency.len()];
+    for root in 0..adjacency.len() {
+        if colors[root].is_some() {
+            continue;
+        }
+        colors[root] = Some(0);
+        let mut queue = VecDeque::from([root]);
+        while let Some(i) = queue.pop_front() {
+            let other = 1 - colors[i]?;
+            for &j in &adjacency[i] {
+                match colors[j] {
+                    None => {
+                        colors[j] = Some(other);
+                        queue.push_back(j);
+                    }
+                    Some(c) if c != other => return None,
+                    Some(_) => {}
+                }
+            }
+        }
+    }
+    colors.into_iter().collect()
+}
+
+// Welsh-Powell: every intersection takes the smallest color none of its
+// already colored neighbours has.
+fn greedy(adjacency: &[Vec<usize>]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..adjacency.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(adjacency[i].len()));
+
+    let mut colors = vec![usize::MAX; adjacency.len()];
+    let mut taken = Vec::new();
+    for i in order {
+        taken.clear();
+        taken.extend(adjacency[i].iter().map(|&j| colors[j]));
+        colors[i] = (0..).find(|c| !taken.contains(c)).unwrap();
+    }
+    colors
+}
+
+impl<T: Scalar> Sparse<T> {
+    pub fn colored_gauss_seidel(
+        &self,
+        b: &[T],
+        x0: &[T],
+        colors: &[Vec<usize>],
+        threads: usize,
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        let stop = StoppingCriterion::new(StoppingTest::AbsoluteStep(eps), max_iter);
+        self.colored_gauss_seidel_until(b, x0, colors, threads, &stop)
+    }
+
+    /// Gauss-Seidel with the rows of one color updated in parallel on up
+    /// to `threads` threads. The colors have to come from the same park
+    /// as the matrix, e.g. Config::coloring.
+    pub fn colored_gauss_seidel_until(
+        &self,
+        b: &[T],
+        x0: &[T],
+        colors: &[Vec<usize>],
+        threads: usize,
+        stop: &StoppingCriterion,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        let rows = self.rows(b.len());
+        if (0..b.len()).any(|i| self.get_value(i, i) == T::ZERO) {
+            return Err(MatrixError::ZeroPivotError);
+        }
+
+        let mut monitor = Monitor::new(stop, b);
+        let mut x = x0.to_vec();
+
+        while monitor.running() {
+            let prev = x.clone();
+            for class in colors {
+                colored_update(&rows, b, &mut x, class, threads);
+            }
+            monitor.update(&prev, &x, |v| self.multiply_by_vec(v));
+        }
+
+        let ax = self.multiply_by_vec(&x);
+        monitor.finish(x, &ax)
+    }
+}
+
+fn colored_update<T: Scalar>(
+    rows: &[Vec<(usize, T)>],
+    b: &[T],
+    x: &mut [T],
+    class: &[usize],
+    threads: usize,
+) {
+    let updated: Vec<T> = {
+        let x = &*x;
+        parallel::pool(threads).install(|| {
+            class
+                .par_iter()
+                .with_min_len(MIN_ROWS_PER_TASK)
+                .map(|&i| row_update(&rows[i], b[i], i, x))
+                .collect()
+        })
+    };
+
+    for (&i, val) in class.iter().zip(updated) {
+        x[i] = val;
+    }
+}
+
+fn row_update<T: Scalar>(row: &[(usize, T)], b: T, i: usize, x: &[T]) -> T {
+    let mut sum = b;
+    let mut diagonal = T::ONE;
+    for &(j, val) in row {
+        if j == i {
+            diagonal = val;
+        } else {
+            sum -= val * x[j];
+        }
+    }
+    sum / diagonal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_proper(cfg: &Config, classes: &[Vec<usize>]) -> bool {
+        let mut color = vec![usize::MAX; cfg.inters.len()];
+        for (c, class) in classes.iter().enumerate() {
+            for &i in class {
+                color[i] = c;
+            }
+        }
+        let adjacency = cfg.adjacency();
+        color.iter().all(|&c| c != usize::MAX)
+            && (0..color.len()).all(|i| adjacency[i].iter().all(|&j| color[j] != color[i]))
+    }
+
+    #[test]
+    fn test_coloring() {
+        // A path is bipartite, a triangle in the middle needs three colors.
+        let path = Config::build(crate::Sets(vec![
+            vec![
+                vec![6, 5],
+                vec![1, 2, 1],
+                vec![2, 3, 1],
+                vec![3, 4, 1],
+                vec![4, 5, 1],
+                vec![5, 6, 1],
+            ],
+            vec![vec![1, 6], vec![1, 1], vec![1, 3]],
+        ]));
+        let classes = path.coloring();
+        assert_eq!(classes.len(), 2);
+        assert!(is_proper(&path, &classes));
+
+        let triangle = Config::build(crate::Sets(vec![
+            vec![
+                vec![5, 5],
+                vec![1, 2, 1],
+                vec![2, 3, 2],
+                vec![3, 4, 1],
+                vec![4, 2, 3],
+                vec![4, 5, 1],
+            ],
+            vec![vec![1, 5], vec![1, 1], vec![1, 3]],
+        ]));
+        let classes = triangle.coloring();
+        assert_eq!(classes.len(), 3);
+        assert!(is_proper(&triangle, &classes));
+    }
+
+    #[test]
+    fn test_colored_gauss_seidel() {
+        let cfg = Config::build(crate::parse_config("default.config"));
+        let colors = cfg.coloring();
+        assert!(is_proper(&cfg, &colors));
+
+        let (sparse, b) = Sparse::from_config(&cfg);
+        let x0 = vec![0f64; b.len()];
+        let report = sparse
+            .colored_gauss_seidel(&b, &x0, &colors, 8, 1e-12, 10_000)
+            .unwrap();
+        assert!(report.residual_norm < 1e-9);
+
+        // Colors of a few thousand rows are split across the threads.
+        let n = 4096;
+        let path = Sparse::init_default_path(n);
+        let mut b = vec![0f64; n];
+        b[0] = 1f64;
+        let x0 = vec![0f64; n];
+        let red_black = vec![(0..n).step_by(2).collect(), (1..n).step_by(2).collect()];
+        let sweeps =
+            |threads| match path.colored_gauss_seidel(&b, &x0, &red_black, threads, 0f64, 20) {
+                Err(MatrixError::NotConverged(report)) => report.solution,
+                _ => unreachable!(),
+            };
+        assert_eq!(sweeps(1), sweeps(4));
+    }
+}
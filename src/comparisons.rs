@@ -1,7 +1,18 @@
+use crate::amg::Amg;
+use crate::banded::Banded;
 use crate::base::*;
+use crate::kaczmarz::RowOrder;
 use crate::matrix::*;
 use crate::monte_carlo;
+use crate::operator::{self, ParkOperator};
+use crate::parallel;
+use crate::preconditioner::{Ic0, Ilu0, Jacobi};
+use crate::rational;
+use crate::refinement::RefinementMode;
+use crate::scalar::{self, DoubleDouble, Scalar};
 use crate::sparse::*;
+use crate::spectral::{self, ConvergenceEstimate, StationaryMethod};
+use crate::stopping::{Norm, StoppingCriterion, StoppingTest};
 use crate::Config;
 
 use std::process;
@@ -21,6 +32,89 @@ pub fn compare_vecs(a: &Vec<f64>, b: &Vec<f64>, eps: f64) -> bool {
     true
 }
 
+// Comparisons time every solver, converged or not, so a solver that ran
+// out of iterations is logged and its last iterate is used anyway.
+fn last_iterate(
+    name: &str,
+    result: Result<SolveReport, MatrixError>,
+) -> Result<Vec<f64>, MatrixError> {
+    match result {
+        Ok(report) => Ok(report.solution),
+        Err(MatrixError::NotConverged(report)) => {
+            eprintln!(
+                "{}: no convergence after {} iterations, residual norm {:e}",
+                name, report.iterations, report.residual_norm
+            );
+            Ok(report.solution)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+// The step size test stops the stationary methods on slowly converging
+// parks long before the probabilities are right, so the comparisons that
+// report accuracy stop them on the residual instead.
+const STATIONARY_TOLERANCE: f64 = 1e-14;
+// check_results only computes κ₁ from the whole inverse up to this size.
+const EXACT_CONDITION_SIZE: usize = 500;
+// Rational elimination gets slow quickly as the fractions grow.
+const EXACT_RATIONAL_SIZE: usize = 200;
+// Lanczos steps for the park spectrum. λ₂ is an extreme eigenvalue, so it
+// settles long before the smaller ones do.
+const SPECTRUM_STEPS: usize = 60;
+// Intersections per block of the domain decomposition, each block is
+// factored once and substituted on every step.
+const SCHWARZ_BLOCK_SIZE: usize = 50;
+// Both row orders of the Kaczmarz sweeps, with the names they're printed as.
+const KACZMARZ_ORDERS: [(&str, RowOrder); 2] = [
+    ("kaczmarz", RowOrder::Cyclic),
+    ("randomized kaczmarz", RowOrder::Randomized),
+];
+
+fn stationary_stop(max_iter: usize) -> StoppingCriterion {
+    StoppingCriterion::new(
+        StoppingTest::RelativeResidual(STATIONARY_TOLERANCE),
+        max_iter,
+    )
+}
+
+// The timings in dump/ stop on the step, as they always have. A residual
+// test would add a product with A to every timed iteration.
+fn timing_stop(eps: f64, max_iter: usize) -> StoppingCriterion {
+    StoppingCriterion::new(StoppingTest::AbsoluteStep(eps), max_iter)
+}
+
+// Times a stationary method, unless its convergence estimate says it can
+// only diverge. A skipped run shows up as NaN in the results and timings.
+fn run_stationary<F>(
+    name: &str,
+    estimate: &ConvergenceEstimate,
+    max_iter: usize,
+    n: usize,
+    solve: F,
+) -> Result<(Vec<f64>, f64), MatrixError>
+where
+    F: FnOnce() -> Result<SolveReport, MatrixError>,
+{
+    match estimate.predicted_iterations(STATIONARY_TOLERANCE) {
+        None => {
+            eprintln!(
+                "{}: spectral radius {} >= 1, skipping",
+                name, estimate.spectral_radius
+            );
+            return Ok((vec![f64::NAN; n], f64::NAN));
+        }
+        Some(iterations) if iterations > max_iter => eprintln!(
+            "{}: needs about {} iterations, only {} allowed",
+            name, iterations, max_iter
+        ),
+        Some(_) => (),
+    }
+
+    let (result, elapsed) = timed(solve);
+    Ok((last_iterate(name, result)?, elapsed))
+}
+
 pub fn compare_config(
     cfg: &Config,
     eps: f64,
@@ -29,99 +123,165 @@ pub fn compare_config(
     let (mat, b) = Matrix::from_config(cfg);
     let (sparse, _) = Sparse::from_config(cfg);
     let x0 = vec![0f64; b.len()];
+    let stop = timing_stop(eps, max_iter);
+    let jacobi_estimate = ConvergenceEstimate::of(&sparse, StationaryMethod::Jacobi, 1_000);
+    let seidel_estimate = ConvergenceEstimate::of(&sparse, StationaryMethod::GaussSeidel, 1_000);
 
-    let gpp_start = Instant::now();
-    let gpp_result = mat.gaussian_partial_pivot(&b);
-    let gpp_elapsed = gpp_start.elapsed().as_secs_f64() * 1000.0
-        + f64::from(gpp_start.elapsed().subsec_nanos()) / 1_000_000.0;
+    let (gpp_result, gpp_elapsed) = timed(|| mat.gaussian_partial_pivot(&b));
 
     let gpp_result = match gpp_result {
         Ok(values) => values,
         Err(e) => return Err(e),
     };
 
-    let gpp_sparse_start = Instant::now();
-    let gpp_sparse_res = sparse.gaussian_partial_pivot(&b);
-    let gpp_sparse_elapsed = gpp_sparse_start.elapsed().as_secs_f64() * 1000.0
-        + f64::from(gpp_sparse_start.elapsed().subsec_nanos()) / 1_000_000.0;
+    let (gpp_sparse_res, gpp_sparse_elapsed) = timed(|| sparse.gaussian_partial_pivot(&b));
 
     let gpp_sparse_res = match gpp_sparse_res {
         Ok(values) => values,
         Err(e) => return Err(e),
     };
 
-    let jacobi_start = Instant::now();
-    let jacobi_res = mat.jacobi(&b, &x0, eps, max_iter);
-    let jacobi_elapsed = jacobi_start.elapsed().as_secs_f64() * 1000.0
-        + f64::from(jacobi_start.elapsed().subsec_nanos()) / 1_000_000.0;
+    let (jacobi_res, jacobi_elapsed) =
+        run_stationary("jacobi", &jacobi_estimate, max_iter, b.len(), || {
+            mat.jacobi_until(&b, &x0, &stop)
+        })?;
+
+    let (jacobi_sparse_res, jacobi_sparse_elapsed) =
+        run_stationary("sparse jacobi", &jacobi_estimate, max_iter, b.len(), || {
+            sparse.jacobi_until(&b, &x0, &stop)
+        })?;
+
+    // Same iterations on every core, only timed next to the serial runs.
+    let threads = parallel::available_threads();
+    let (_, jacobi_par_elapsed) = run_stationary(
+        "parallel jacobi",
+        &jacobi_estimate,
+        max_iter,
+        b.len(),
+        || mat.par_jacobi_until(&b, &x0, threads, &stop),
+    )?;
 
-    let jacobi_sparse_start = Instant::now();
-    let jacobi_sparse_res = sparse.jacobi(&b, &x0, eps, max_iter);
-    let jacobi_sparse_elapsed = jacobi_sparse_start.elapsed().as_secs_f64() * 1000.0
-        + f64::from(jacobi_sparse_start.elapsed().subsec_nanos()) / 1_000_000.0;
+    let (_, jacobi_sparse_par_elapsed) = run_stationary(
+        "parallel sparse jacobi",
+        &jacobi_estimate,
+        max_iter,
+        b.len(),
+        || sparse.par_jacobi_until(&b, &x0, threads, &stop),
+    )?;
 
-    let seidel_start = Instant::now();
-    let seidel_res = mat.gauss_seidel(&b, &x0, eps, max_iter);
-    let seidel_elapsed = seidel_start.elapsed().as_secs_f64() * 1000.0
-        + f64::from(seidel_start.elapsed().subsec_nanos()) / 1_000_000.0;
+    let (seidel_res, seidel_elapsed) =
+        run_stationary("gauss-seidel", &seidel_estimate, max_iter, b.len(), || {
+            mat.gauss_seidel_until(&b, &x0, &stop)
+        })?;
 
-    let gauss_start = Instant::now();
-    let gauss_res = mat.gaussian(&b);
-    let gauss_elapsed = gauss_start.elapsed().as_secs_f64() * 1000.0
-        + f64::from(gauss_start.elapsed().subsec_nanos()) / 1_000_000.0;
+    let (gauss_res, gauss_elapsed) = timed(|| mat.gaussian(&b));
 
     let gauss_res = match gauss_res {
         Ok(values) => values,
         Err(e) => return Err(e),
     };
 
-    let gauss_sparse_start = Instant::now();
-    let gauss_sparse_res = sparse.gaussian(&b);
-    let gauss_sparse_elapsed = gauss_sparse_start.elapsed().as_secs_f64() * 1000.0
-        + f64::from(gauss_sparse_start.elapsed().subsec_nanos()) / 1_000_000.0;
+    let (gauss_sparse_res, gauss_sparse_elapsed) = timed(|| sparse.gaussian(&b));
 
     let gauss_sparse_res = match gauss_sparse_res {
         Ok(values) => values,
         Err(e) => return Err(e),
     };
 
-    let seidel_sparse_start = Instant::now();
-    let seidel_sparse_res = mat.gauss_seidel(&b, &x0, eps, max_iter);
-    let seidel_sparse_elapsed = seidel_sparse_start.elapsed().as_secs_f64() * 1000.0
-        + f64::from(seidel_sparse_start.elapsed().subsec_nanos()) / 1_000_000.0;
+    let (seidel_sparse_res, seidel_sparse_elapsed) = run_stationary(
+        "sparse gauss-seidel",
+        &seidel_estimate,
+        max_iter,
+        b.len(),
+        || sparse.gauss_seidel_until(&b, &x0, &stop),
+    )?;
+
+    let (gcp_res, gcp_elapsed) = timed(|| mat.gaussian_complete_pivot(&b));
+    let gcp_res = gcp_res?;
+
+    let (gcp_sparse_res, gcp_sparse_elapsed) = timed(|| sparse.gaussian_complete_pivot(&b));
+    let gcp_sparse_res = gcp_sparse_res?;
+
+    let (gsp_res, gsp_elapsed) = timed(|| mat.gaussian_scaled_pivot(&b));
+    let gsp_res = gsp_res?;
+
+    let (gsp_sparse_res, gsp_sparse_elapsed) = timed(|| sparse.gaussian_scaled_pivot(&b));
+    let gsp_sparse_res = gsp_sparse_res?;
+
+    // A randomized sweep can leave x where it was, so Kaczmarz can't stop
+    // on the step like the stationary runs above.
+    let kaczmarz_stop = stationary_stop(max_iter);
+    let (kaczmarz_res, kaczmarz_elapsed) =
+        timed(|| mat.kaczmarz_until(&b, &x0, RowOrder::Cyclic, &kaczmarz_stop));
+    let kaczmarz_res = last_iterate("kaczmarz", kaczmarz_res)?;
+    let (kaczmarz_sparse_res, kaczmarz_sparse_elapsed) =
+        timed(|| sparse.kaczmarz_until(&b, &x0, RowOrder::Cyclic, &kaczmarz_stop));
+    let kaczmarz_sparse_res = last_iterate("sparse kaczmarz", kaczmarz_sparse_res)?;
+    let (randomized_res, randomized_elapsed) =
+        timed(|| mat.kaczmarz_until(&b, &x0, RowOrder::Randomized, &kaczmarz_stop));
+    let randomized_res = last_iterate("randomized kaczmarz", randomized_res)?;
+    let (randomized_sparse_res, randomized_sparse_elapsed) =
+        timed(|| sparse.kaczmarz_until(&b, &x0, RowOrder::Randomized, &kaczmarz_stop));
+    let randomized_sparse_res = last_iterate("sparse randomized kaczmarz", randomized_sparse_res)?;
+
+    // Not timed, the same for both representations.
+    let cond = sparse.condition_estimate()?;
+    let lambda2 = cfg.spectrum(SPECTRUM_STEPS).second_eigenvalue;
 
     let res_ns_line = format!(
-        "{};{};{};{};{}",
+        "{};{};{};{};{};{};{};{:e};{};{};{}",
         b.len(),
         jacobi_res[cfg.starting_pos],
         seidel_res[cfg.starting_pos],
         gauss_res[cfg.starting_pos],
         gpp_result[cfg.starting_pos],
+        gcp_res[cfg.starting_pos],
+        gsp_res[cfg.starting_pos],
+        cond,
+        lambda2,
+        kaczmarz_res[cfg.starting_pos],
+        randomized_res[cfg.starting_pos],
     );
     let time_ns_line = format!(
-        "{};{};{};{};{}",
+        "{};{};{};{};{};{};{};{};{};{}",
         b.len(),
         jacobi_elapsed,
         seidel_elapsed,
         gauss_elapsed,
-        gpp_elapsed
+        gpp_elapsed,
+        gcp_elapsed,
+        gsp_elapsed,
+        jacobi_par_elapsed,
+        kaczmarz_elapsed,
+        randomized_elapsed
     );
 
     let res_s_line = format!(
-        "{};{};{};{};{}",
+        "{};{};{};{};{};{};{};{:e};{};{};{}",
         b.len(),
         jacobi_sparse_res[cfg.starting_pos],
         seidel_sparse_res[cfg.starting_pos],
         gauss_sparse_res[cfg.starting_pos],
-        gpp_sparse_res[cfg.starting_pos]
+        gpp_sparse_res[cfg.starting_pos],
+        gcp_sparse_res[cfg.starting_pos],
+        gsp_sparse_res[cfg.starting_pos],
+        cond,
+        lambda2,
+        kaczmarz_sparse_res[cfg.starting_pos],
+        randomized_sparse_res[cfg.starting_pos],
     );
     let time_s_line = format!(
-        "{};{};{};{};{}",
+        "{};{};{};{};{};{};{};{};{};{}",
         b.len(),
         jacobi_sparse_elapsed,
         seidel_sparse_elapsed,
         gauss_sparse_elapsed,
-        gpp_sparse_elapsed
+        gpp_sparse_elapsed,
+        gcp_sparse_elapsed,
+        gsp_sparse_elapsed,
+        jacobi_sparse_par_elapsed,
+        kaczmarz_sparse_elapsed,
+        randomized_sparse_elapsed
     );
 
     Ok((res_ns_line, time_ns_line, res_s_line, time_s_line))
@@ -137,10 +297,18 @@ pub fn incremental_compare_config(
     let mut results_s = Vec::new();
     let mut times_s = Vec::new();
 
-    results_ns.push(String::from("n;jacobi;seidel;gauss;gauss_pivot"));
-    times_ns.push(String::from("n;jacobi;seidel;gauss;gauss_pivot"));
-    results_s.push(String::from("n;jacobi;seidel;gauss;gauss_pivot"));
-    times_s.push(String::from("n;jacobi;seidel;gauss;gauss_pivot"));
+    results_ns.push(String::from(
+        "n;jacobi;seidel;gauss;gauss_pivot;gauss_complete_pivot;gauss_scaled_pivot;cond;lambda2;kaczmarz;kaczmarz_randomized",
+    ));
+    times_ns.push(String::from(
+        "n;jacobi;seidel;gauss;gauss_pivot;gauss_complete_pivot;gauss_scaled_pivot;jacobi_parallel;kaczmarz;kaczmarz_randomized",
+    ));
+    results_s.push(String::from(
+        "n;jacobi;seidel;gauss;gauss_pivot;gauss_complete_pivot;gauss_scaled_pivot;cond;lambda2;kaczmarz;kaczmarz_randomized",
+    ));
+    times_s.push(String::from(
+        "n;jacobi;seidel;gauss;gauss_pivot;gauss_complete_pivot;gauss_scaled_pivot;jacobi_parallel;kaczmarz;kaczmarz_randomized",
+    ));
 
     let eps = 1e-16;
     let max_iter = 10_000;
@@ -218,46 +386,34 @@ fn compare_default(
     let mut b = vec![0f64; n];
     b[0] = 1f64;
     let x0 = vec![0f64; n];
+    let stop = timing_stop(eps, max_iter);
 
-    let jacobi_start = Instant::now();
-    let jacobi_result = mat.jacobi(&b, &x0, eps, max_iter)[starting_pos];
-    let jacobi_elapsed = jacobi_start.elapsed().as_secs_f64() * 1000.0
-        + f64::from(jacobi_start.elapsed().subsec_nanos()) / 1_000_000.0;
-
-    let jacobi_sparse_start = Instant::now();
-    let jacobi_sparse_result = sparse_mat.jacobi(&b, &x0, eps, max_iter)[starting_pos];
-    let jacobi_sparse_elapsed = jacobi_sparse_start.elapsed().as_secs_f64() * 1000.0
-        + f64::from(jacobi_sparse_start.elapsed().subsec_nanos()) / 1_000_000.0;
-
-    let seidel_start = Instant::now();
-    let seidel_result = mat.gauss_seidel(&b, &x0, eps, max_iter)[starting_pos];
-    let seidel_elapsed = seidel_start.elapsed().as_secs_f64() * 1000.0
-        + f64::from(seidel_start.elapsed().subsec_nanos()) / 1_000_000.0;
-
-    let gauss_start = Instant::now();
-    let gauss_result = mat.gaussian(&b)?[starting_pos];
-    let gauss_elapsed = gauss_start.elapsed().as_secs_f64() * 1000.0
-        + f64::from(gauss_start.elapsed().subsec_nanos()) / 1_000_000.0;
-
-    let g_sparse_start = Instant::now();
-    let g_sparse_result = sparse_mat.gaussian(&b)?[starting_pos];
-    let g_sparse_elapsed = g_sparse_start.elapsed().as_secs_f64() * 1000.0
-        + f64::from(g_sparse_start.elapsed().subsec_nanos()) / 1_000_000.0;
-
-    let gpp_start = Instant::now();
-    let gauss_partial_pivot_result = mat.gaussian_partial_pivot(&b)?[starting_pos];
-    let gpp_elapsed = gpp_start.elapsed().as_secs_f64() * 1000.0
-        + f64::from(gpp_start.elapsed().subsec_nanos()) / 1_000_000.0;
-
-    let gpp_sparse_start = Instant::now();
-    let gpp_sparse_result = sparse_mat.gaussian_partial_pivot(&b)?[starting_pos];
-    let gpp_sparse_elapsed = gpp_sparse_start.elapsed().as_secs_f64() * 1000.0
-        + f64::from(gpp_sparse_start.elapsed().subsec_nanos()) / 1_000_000.0;
-
-    let seidel_sparse_start = Instant::now();
-    let seidel_sparse_result = mat.gauss_seidel(&b, &x0, eps, max_iter)[starting_pos];
-    let seidel_sparse_elapsed = seidel_sparse_start.elapsed().as_secs_f64() * 1000.0
-        + f64::from(seidel_sparse_start.elapsed().subsec_nanos()) / 1_000_000.0;
+    let (jacobi_result, jacobi_elapsed) = timed(|| mat.jacobi_until(&b, &x0, &stop));
+    let jacobi_result = last_iterate("jacobi", jacobi_result)?[starting_pos];
+
+    let (jacobi_sparse_result, jacobi_sparse_elapsed) =
+        timed(|| sparse_mat.jacobi_until(&b, &x0, &stop));
+    let jacobi_sparse_result = last_iterate("sparse jacobi", jacobi_sparse_result)?[starting_pos];
+
+    let (seidel_result, seidel_elapsed) = timed(|| mat.gauss_seidel_until(&b, &x0, &stop));
+    let seidel_result = last_iterate("gauss-seidel", seidel_result)?[starting_pos];
+
+    let (gauss_result, gauss_elapsed) = timed(|| mat.gaussian(&b));
+    let gauss_result = gauss_result?[starting_pos];
+
+    let (g_sparse_result, g_sparse_elapsed) = timed(|| sparse_mat.gaussian(&b));
+    let g_sparse_result = g_sparse_result?[starting_pos];
+
+    let (gauss_partial_pivot_result, gpp_elapsed) = timed(|| mat.gaussian_partial_pivot(&b));
+    let gauss_partial_pivot_result = gauss_partial_pivot_result?[starting_pos];
+
+    let (gpp_sparse_result, gpp_sparse_elapsed) = timed(|| sparse_mat.gaussian_partial_pivot(&b));
+    let gpp_sparse_result = gpp_sparse_result?[starting_pos];
+
+    let (seidel_sparse_result, seidel_sparse_elapsed) =
+        timed(|| sparse_mat.gauss_seidel_until(&b, &x0, &stop));
+    let seidel_sparse_result =
+        last_iterate("sparse gauss-seidel", seidel_sparse_result)?[starting_pos];
 
     let non_sparse_row = format!(
         "{};{};{};{};{}",
@@ -287,6 +443,27 @@ fn compare_default(
     ))
 }
 
+// Only the banded solvers, they are the ones that scale to long paths.
+fn compare_default_banded(
+    n: usize,
+    starting_pos: usize,
+) -> Result<(String, String), Box<dyn Error>> {
+    let banded = Banded::init_default_path(n);
+    let mut b = vec![0f64; n];
+    b[0] = 1f64;
+
+    let (thomas_result, thomas_elapsed) = timed(|| banded.solve_tridiagonal(&b));
+    let (band_lu_result, band_lu_elapsed) = timed(|| banded.lu()?.solve(&b));
+
+    Ok((
+        format!("{};{};{}", n, thomas_elapsed, band_lu_elapsed),
+        format!(
+            "{};{};{}",
+            n, thomas_result?[starting_pos], band_lu_result?[starting_pos]
+        ),
+    ))
+}
+
 pub fn incremental_compare_default() {
     let mut ns_row_lines = Vec::new();
     let mut ns_res_lines = Vec::new();
@@ -322,6 +499,24 @@ pub fn incremental_compare_default() {
     fs::write("dump/default_no_sparse_results.csv", ns_res_str).unwrap();
     fs::write("dump/default_sparse_time.csv", s_row_str).unwrap();
     fs::write("dump/default_sparse_results.csv", s_res_str).unwrap();
+
+    let mut banded_row_lines = vec![String::from("n;thomas;band_lu")];
+    let mut banded_res_lines = vec![String::from("n;thomas;band_lu")];
+
+    let mut n = 10;
+    while n <= 1_000_000 {
+        let (row, res) = compare_default_banded(n, n / 2).unwrap();
+        banded_row_lines.push(row);
+        banded_res_lines.push(res);
+        n *= 2;
+    }
+
+    fs::write("dump/default_banded_time.csv", banded_row_lines.join("\n")).unwrap();
+    fs::write(
+        "dump/default_banded_results.csv",
+        banded_res_lines.join("\n"),
+    )
+    .unwrap();
 }
 
 pub fn incremental_verify_mc(n: usize, cfg: Option<&Config>) -> Result<(), Box<dyn Error>> {
@@ -345,7 +540,7 @@ pub fn incremental_verify_mc(n: usize, cfg: Option<&Config>) -> Result<(), Box<d
             }
         };
 
-        let (sparse, b) = Sparse::from_config(&config);
+        let (sparse, b) = Sparse::<f64>::from_config(&config);
         let sparse_res = match sparse.gaussian_partial_pivot(&b) {
             Ok(values) => values[config.starting_pos],
             Err(e) => return Err(Box::new(e)),
@@ -374,6 +569,7 @@ pub fn check_results(config: &Config) {
     let x0 = vec![0f64; b.len()];
     let eps = 1e-16;
     let max_iter = 10_000;
+    let stop = stationary_stop(max_iter);
 
     let gpp_res = match mat.gaussian_partial_pivot(&b) {
         Ok(values) => values,
@@ -391,13 +587,39 @@ pub fn check_results(config: &Config) {
         }
     };
     let gpp_sparse_sub = mat.multiply_by_vec(&gpp_sparse_res).unwrap();
-    let jacobi_res = mat.jacobi(&b, &x0, eps, max_iter);
+    let jacobi_res = match last_iterate("jacobi", mat.jacobi_until(&b, &x0, &stop)) {
+        Ok(values) => values,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(0);
+        }
+    };
     let jacobi_sub = mat.multiply_by_vec(&jacobi_res).unwrap();
-    let jacobi_sparse_res = sparse.jacobi(&b, &x0, eps, max_iter);
+    let jacobi_sparse_run = sparse.jacobi_until(&b, &x0, &stop);
+    let jacobi_sparse_res = match last_iterate("sparse jacobi", jacobi_sparse_run) {
+        Ok(values) => values,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(0);
+        }
+    };
     let jacobi_sparse_sub = mat.multiply_by_vec(&jacobi_sparse_res).unwrap();
-    let seidel_res = mat.gauss_seidel(&b, &x0, eps, max_iter);
+    let seidel_res = match last_iterate("gauss-seidel", mat.gauss_seidel_until(&b, &x0, &stop)) {
+        Ok(values) => values,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(0);
+        }
+    };
     let seidel_sub = mat.multiply_by_vec(&seidel_res).unwrap();
-    let seidel_sparse_res = sparse.gauss_seidel(&b, &x0, eps, max_iter);
+    let seidel_sparse_run = sparse.gauss_seidel_until(&b, &x0, &stop);
+    let seidel_sparse_res = match last_iterate("sparse gauss-seidel", seidel_sparse_run) {
+        Ok(values) => values,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(0);
+        }
+    };
     let seidel_sparse_sub = mat.multiply_by_vec(&seidel_sparse_res).unwrap();
     let gauss_res = match mat.gaussian(&b) {
         Ok(values) => values,
@@ -456,19 +678,147 @@ pub fn check_results(config: &Config) {
     } else {
         println!("Sparse Gauss: Failure")
     }
+    if config.chain_order().is_some() {
+        match config.solve() {
+            Ok(values) if compare_vecs(&mat.multiply_by_vec(&values).unwrap(), &b, eps) => {
+                println!("Banded Thomas (chain): Success")
+            }
+            Ok(_) => println!("Banded Thomas (chain): Failure"),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+
+    // Measured against the exact fractions instead of against each other.
+    if b.len() <= EXACT_RATIONAL_SIZE {
+        match config.exact_solve() {
+            Ok(exact) => {
+                println!(
+                    "\nExact probability from the start: {}",
+                    exact[config.starting_pos]
+                );
+                let exact = rational::to_f64(&exact);
+                println!("Forward error against the exact solution (infinity norm):");
+                for (name, res) in [
+                    ("Gauss (partial pivot)", &gpp_res),
+                    ("Sparse Gauss (partial pivot)", &gpp_sparse_res),
+                    ("Jacobi", &jacobi_res),
+                    ("Sparse Jacobi", &jacobi_sparse_res),
+                    ("Gauss-Seidel", &seidel_res),
+                    ("Sparse Gauss-Seidel", &seidel_sparse_res),
+                    ("Gauss", &gauss_res),
+                    ("Sparse Gauss", &gauss_sparse_res),
+                ] {
+                    println!("{}: {:e}", name, Norm::Inf.of_difference(res, &exact));
+                }
+
+                let (sym, sym_b) = Sparse::from_config_symmetric(config);
+                let restart = 30;
+                for (name, res) in [
+                    ("Gauss (complete pivot)", mat.gaussian_complete_pivot(&b)),
+                    (
+                        "Sparse Gauss (complete pivot)",
+                        sparse.gaussian_complete_pivot(&b),
+                    ),
+                    ("Gauss (scaled pivot)", mat.gaussian_scaled_pivot(&b)),
+                    (
+                        "Sparse Gauss (scaled pivot)",
+                        sparse.gaussian_scaled_pivot(&b),
+                    ),
+                    (
+                        "CG (symmetric form)",
+                        last_iterate(
+                            "cg",
+                            sym.conjugate_gradient(&sym_b, &x0, STATIONARY_TOLERANCE, max_iter),
+                        ),
+                    ),
+                    (
+                        "GMRES",
+                        last_iterate(
+                            "gmres",
+                            sparse.gmres(&b, &x0, restart, STATIONARY_TOLERANCE, max_iter),
+                        ),
+                    ),
+                    (
+                        "BiCGSTAB",
+                        last_iterate(
+                            "bicgstab",
+                            sparse.bicgstab(&b, &x0, STATIONARY_TOLERANCE, max_iter),
+                        ),
+                    ),
+                    (
+                        "SOR",
+                        last_iterate("sor", sparse.sor_until(&b, &x0, Relaxation::Auto, &stop)),
+                    ),
+                ] {
+                    match res {
+                        Ok(res) => {
+                            println!("{}: {:e}", name, Norm::Inf.of_difference(&res, &exact))
+                        }
+                        Err(e) => eprintln!("{}: {}", name, e),
+                    }
+                }
+            }
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+
+    // compare_vecs with eps = 1e-16 fails on rounding alone, the
+    // refinement errors tell whether the solution is actually accurate.
+    for (name, mode) in [
+        ("mixed", RefinementMode::Mixed),
+        ("compensated", RefinementMode::Compensated),
+    ] {
+        match sparse.refine(&b, mode, 10) {
+            Ok(report) => {
+                println!("\nRefinement ({}):", name);
+                for (i, step) in report.steps.iter().enumerate() {
+                    println!(
+                        "step {}: forward error {:e}, backward error {:e}",
+                        i, step.forward_error, step.backward_error
+                    );
+                }
+            }
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+
+    // With κ₁ around 1/ε the solvers can't be expected to agree anymore,
+    // whatever the pivoting.
+    println!();
+    match sparse.condition_estimate() {
+        Ok(cond) => println!("Condition number (1-norm, estimated): {:e}", cond),
+        Err(e) => eprintln!("{}", e),
+    }
+    if b.len() <= EXACT_CONDITION_SIZE {
+        match mat.condition_number() {
+            Ok(cond) => println!("Condition number (1-norm, exact): {:e}", cond),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+
+    // λ₂ is also ρ of the Jacobi iteration matrix, every Jacobi step
+    // shrinks the error by about that factor.
+    let spectrum = config.spectrum(SPECTRUM_STEPS);
+    println!(
+        "Second eigenvalue of the walk: {} (smallest: {})",
+        spectrum.second_eigenvalue, spectrum.smallest_eigenvalue
+    );
+    println!("Relaxation time: {} steps", spectrum.relaxation_time);
+    match spectral::iterations_for(spectrum.second_eigenvalue, 1e-10) {
+        Some(iterations) => println!("Predicted Jacobi iterations for 1e-10: {}", iterations),
+        // λ₂ = 1 when some intersection can reach neither an exit nor a well.
+        None => println!("Predicted Jacobi iterations for 1e-10: never"),
+    }
 }
 
 pub fn time_all(config: &Config) {
     let (mat, _) = Matrix::from_config(&config);
     let (sparse, b) = Sparse::from_config(&config);
     let x0 = vec![0f64; b.len()];
-    let eps = 1e-16;
     let max_iter = 1_000;
+    let stop = stationary_stop(max_iter);
 
-    let gpp_start = Instant::now();
-    let gpp_result = mat.gaussian_partial_pivot(&b);
-    let gpp_elapsed = gpp_start.elapsed().as_secs_f64() * 1000.0
-        + f64::from(gpp_start.elapsed().subsec_nanos()) / 1_000_000.0;
+    let (gpp_result, gpp_elapsed) = timed(|| mat.gaussian_partial_pivot(&b));
 
     let gpp_result = match gpp_result {
         Ok(values) => values,
@@ -478,10 +828,7 @@ pub fn time_all(config: &Config) {
         }
     };
 
-    let gpp_sparse_start = Instant::now();
-    let gpp_sparse_res = sparse.gaussian_partial_pivot(&b);
-    let gpp_sparse_elapsed = gpp_sparse_start.elapsed().as_secs_f64() * 1000.0
-        + f64::from(gpp_sparse_start.elapsed().subsec_nanos()) / 1_000_000.0;
+    let (gpp_sparse_res, gpp_sparse_elapsed) = timed(|| sparse.gaussian_partial_pivot(&b));
 
     let gpp_sparse_res = match gpp_sparse_res {
         Ok(values) => values,
@@ -491,30 +838,48 @@ pub fn time_all(config: &Config) {
         }
     };
 
-    let jacobi_start = Instant::now();
-    let jacobi_res = mat.jacobi(&b, &x0, eps, max_iter);
-    let jacobi_elapsed = jacobi_start.elapsed().as_secs_f64() * 1000.0
-        + f64::from(jacobi_start.elapsed().subsec_nanos()) / 1_000_000.0;
+    let (jacobi_res, jacobi_elapsed) = timed(|| mat.jacobi_until(&b, &x0, &stop));
 
-    let jacobi_sparse_start = Instant::now();
-    let jacobi_sparse_res = sparse.jacobi(&b, &x0, eps, max_iter);
-    let jacobi_sparse_elapsed = jacobi_sparse_start.elapsed().as_secs_f64() * 1000.0
-        + f64::from(jacobi_sparse_start.elapsed().subsec_nanos()) / 1_000_000.0;
+    let jacobi_res = match last_iterate("jacobi", jacobi_res) {
+        Ok(values) => values,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(0);
+        }
+    };
 
-    let seidel_start = Instant::now();
-    let seidel_res = mat.gauss_seidel(&b, &x0, eps, max_iter);
-    let seidel_elapsed = seidel_start.elapsed().as_secs_f64() * 1000.0
-        + f64::from(seidel_start.elapsed().subsec_nanos()) / 1_000_000.0;
+    let (jacobi_sparse_res, jacobi_sparse_elapsed) = timed(|| sparse.jacobi_until(&b, &x0, &stop));
 
-    let seidel_sparse_start = Instant::now();
-    let seidel_sparse_res = sparse.gauss_seidel(&b, &x0, eps, max_iter);
-    let seidel_sparse_elapsed = seidel_sparse_start.elapsed().as_secs_f64() * 1000.0
-        + f64::from(seidel_sparse_start.elapsed().subsec_nanos()) / 1_000_000.0;
+    let jacobi_sparse_res = match last_iterate("sparse jacobi", jacobi_sparse_res) {
+        Ok(values) => values,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(0);
+        }
+    };
+
+    let (seidel_res, seidel_elapsed) = timed(|| mat.gauss_seidel_until(&b, &x0, &stop));
+
+    let seidel_res = match last_iterate("gauss-seidel", seidel_res) {
+        Ok(values) => values,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(0);
+        }
+    };
 
-    let gauss_start = Instant::now();
-    let gauss_res = mat.gaussian(&b);
-    let gauss_elapsed = gauss_start.elapsed().as_secs_f64() * 1000.0
-        + f64::from(gauss_start.elapsed().subsec_nanos()) / 1_000_000.0;
+    let (seidel_sparse_res, seidel_sparse_elapsed) =
+        timed(|| sparse.gauss_seidel_until(&b, &x0, &stop));
+
+    let seidel_sparse_res = match last_iterate("sparse gauss-seidel", seidel_sparse_res) {
+        Ok(values) => values,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(0);
+        }
+    };
+
+    let (gauss_res, gauss_elapsed) = timed(|| mat.gaussian(&b));
 
     let gauss_res = match gauss_res {
         Ok(values) => values,
@@ -524,10 +889,7 @@ pub fn time_all(config: &Config) {
         }
     };
 
-    let gauss_sparse_start = Instant::now();
-    let gauss_sparse_res = sparse.gaussian(&b);
-    let gauss_sparse_elapsed = gauss_sparse_start.elapsed().as_secs_f64() * 1000.0
-        + f64::from(gauss_sparse_start.elapsed().subsec_nanos()) / 1_000_000.0;
+    let (gauss_sparse_res, gauss_sparse_elapsed) = timed(|| sparse.gaussian(&b));
 
     let gauss_sparse_res = match gauss_sparse_res {
         Ok(values) => values,
@@ -538,10 +900,7 @@ pub fn time_all(config: &Config) {
     };
 
     println!("\nMC:");
-    let mc_start = Instant::now();
-    let mc_res = monte_carlo::simulate_park_walk(&config, 30_000);
-    let mc_elapsed = mc_start.elapsed().as_secs_f64() * 1000.0
-        + f64::from(mc_start.elapsed().subsec_nanos()) / 1_000_000.0;
+    let (mc_res, mc_elapsed) = timed(|| monte_carlo::simulate_park_walk(&config, 30_000));
     println!("mc: {} in {:.6}ms", mc_res, mc_elapsed);
 
     println!("\nMAT:");
@@ -561,6 +920,16 @@ pub fn time_all(config: &Config) {
         "jacobi: {} in {:.6}ms",
         jacobi_res[config.starting_pos], jacobi_elapsed
     );
+    for (name, order) in KACZMARZ_ORDERS {
+        let (result, elapsed) = timed(|| mat.kaczmarz_until(&b, &x0, order, &stop));
+        match last_iterate(name, result) {
+            Ok(values) => println!(
+                "{}: {} in {:.6}ms",
+                name, values[config.starting_pos], elapsed
+            ),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
     println!("\nSPARSE:");
     println!(
         "gauss: {} in {:.6}ms",
@@ -578,4 +947,254 @@ pub fn time_all(config: &Config) {
         "jacobi: {} in {:.6}ms",
         jacobi_sparse_res[config.starting_pos], jacobi_sparse_elapsed
     );
+    for (name, order) in KACZMARZ_ORDERS {
+        let (result, elapsed) = timed(|| sparse.kaczmarz_until(&b, &x0, order, &stop));
+        match last_iterate(name, result) {
+            Ok(values) => println!(
+                "{}: {} in {:.6}ms",
+                name, values[config.starting_pos], elapsed
+            ),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+
+    let threads = parallel::available_threads();
+    let colors = config.coloring();
+    let (result, elapsed) =
+        timed(|| sparse.colored_gauss_seidel_until(&b, &x0, &colors, threads, &stop));
+    match last_iterate("colored gauss-seidel", result) {
+        Ok(values) => println!(
+            "colored gauss seidel ({} colors, {} threads): {} in {:.6}ms",
+            colors.len(),
+            threads,
+            values[config.starting_pos],
+            elapsed
+        ),
+        Err(e) => eprintln!("{}", e),
+    }
+
+    println!("\nSERIAL / PARALLEL ({} threads):", threads);
+    let (result, par_elapsed) = timed(|| mat.par_jacobi_until(&b, &x0, threads, &stop));
+    if let Err(e) = last_iterate("parallel jacobi", result) {
+        eprintln!("{}", e);
+    }
+    println!("jacobi: {:.6}ms / {:.6}ms", jacobi_elapsed, par_elapsed);
+    let (result, par_elapsed) = timed(|| sparse.par_jacobi_until(&b, &x0, threads, &stop));
+    if let Err(e) = last_iterate("parallel sparse jacobi", result) {
+        eprintln!("{}", e);
+    }
+    println!(
+        "sparse jacobi: {:.6}ms / {:.6}ms",
+        jacobi_sparse_elapsed, par_elapsed
+    );
+    let (_, elapsed) = timed(|| mat.multiply_by_vec(&jacobi_res));
+    let (_, par_elapsed) = timed(|| mat.par_multiply_by_vec(&jacobi_res, threads));
+    println!("mat * vec: {:.6}ms / {:.6}ms", elapsed, par_elapsed);
+    let (_, elapsed) = timed(|| sparse.multiply_by_vec(&jacobi_res));
+    let (_, par_elapsed) = timed(|| sparse.par_multiply_by_vec(&jacobi_res, threads));
+    println!("sparse * vec: {:.6}ms / {:.6}ms", elapsed, par_elapsed);
+
+    let blocks = b.len().div_ceil(SCHWARZ_BLOCK_SIZE);
+    println!("\nDOMAIN DECOMPOSITION ({} blocks):", blocks);
+    for (name, overlap) in [("block jacobi", 0), ("schwarz (overlap 1)", 1)] {
+        let decomposition = config.decompose(blocks, overlap);
+        let (result, elapsed) =
+            timed(|| sparse.schwarz_until(&b, &x0, &decomposition, threads, &stop));
+        match result {
+            Ok(report) => println!(
+                "{}: {} in {:.6}ms, {} iterations",
+                name, report.solution[config.starting_pos], elapsed, report.iterations
+            ),
+            Err(e) => eprintln!("{}: {}", name, e),
+        }
+    }
+
+    // Straight from the alleys, nothing is assembled.
+    println!("\nMATRIX-FREE:");
+    let (result, elapsed) = timed(|| operator::jacobi(&ParkOperator::new(config), &b, &x0, &stop));
+    match last_iterate("matrix-free jacobi", result) {
+        Ok(values) => println!(
+            "jacobi: {} in {:.6}ms",
+            values[config.starting_pos], elapsed
+        ),
+        Err(e) => eprintln!("{}", e),
+    }
+    let (result, elapsed) = timed(|| config.solve_matrix_free(STATIONARY_TOLERANCE, max_iter));
+    match result {
+        Ok(report) => println!(
+            "bicgstab: {} in {:.6}ms",
+            report.solution[config.starting_pos], elapsed
+        ),
+        Err(e) => eprintln!("{}", e),
+    }
+}
+
+// Runs f and returns its result together with the elapsed milliseconds.
+fn timed<T>(f: impl FnOnce() -> T) -> (T, f64) {
+    let start = Instant::now();
+    let out = f();
+    (out, start.elapsed().as_secs_f64() * 1000.0)
+}
+
+/// Solves the same park with and without preconditioning. CG runs on the
+/// symmetric form, the other solvers on the system from from_config.
+/// Setup times of the preconditioners are included in the timings.
+pub fn compare_preconditioners(config: &Config) -> Result<(), MatrixError> {
+    let eps = 1e-12;
+    let max_iter = 10_000;
+    let stop =
+        StoppingCriterion::new(StoppingTest::AbsoluteResidual(eps), max_iter).with_norm(Norm::L2);
+    let restart = 30;
+
+    let (sym, sym_b) = Sparse::from_config_symmetric(config);
+    let (sparse, b) = Sparse::from_config(config);
+    let x0 = vec![0f64; b.len()];
+    let pos = config.starting_pos;
+
+    let print =
+        |name: &str, (result, elapsed): (Result<SolveReport, MatrixError>, f64)| match result {
+            Ok(report) => println!(
+                "{}: {} in {:.6}ms, {} iterations",
+                name, report.solution[pos], elapsed, report.iterations
+            ),
+            Err(e) => eprintln!("{}: {}", name, e),
+        };
+
+    println!("\nCG (symmetric form):");
+    print(
+        "none",
+        timed(|| sym.conjugate_gradient(&sym_b, &x0, eps, max_iter)),
+    );
+    print(
+        "jacobi",
+        timed(|| {
+            let precond = Jacobi::from_sparse(&sym)?;
+            sym.preconditioned_conjugate_gradient(&sym_b, &x0, &precond, eps, max_iter)
+        }),
+    );
+    print(
+        "ic(0)",
+        timed(|| {
+            let precond = Ic0::from_sparse(&sym)?;
+            sym.preconditioned_conjugate_gradient(&sym_b, &x0, &precond, eps, max_iter)
+        }),
+    );
+    print(
+        "amg",
+        timed(|| {
+            let precond = Amg::new(&sym)?;
+            sym.preconditioned_conjugate_gradient(&sym_b, &x0, &precond, eps, max_iter)
+        }),
+    );
+
+    println!("\nGMRES({}):", restart);
+    print(
+        "none",
+        timed(|| sparse.gmres(&b, &x0, restart, eps, max_iter)),
+    );
+    print(
+        "ilu(0)",
+        timed(|| {
+            let precond = Ilu0::from_sparse(&sparse)?;
+            sparse.preconditioned_gmres(&b, &x0, &precond, restart, eps, max_iter)
+        }),
+    );
+
+    println!("\nBiCGSTAB:");
+    print("none", timed(|| sparse.bicgstab(&b, &x0, eps, max_iter)));
+    print(
+        "ilu(0)",
+        timed(|| {
+            let precond = Ilu0::from_sparse(&sparse)?;
+            sparse.preconditioned_bicgstab(&b, &x0, &precond, eps, max_iter)
+        }),
+    );
+
+    println!("\nRichardson (stationary):");
+    print(
+        "jacobi method",
+        timed(|| sparse.jacobi_until(&b, &x0, &stop)),
+    );
+    print(
+        "ilu(0)",
+        timed(|| {
+            let precond = Ilu0::from_sparse(&sparse)?;
+            sparse.richardson(&b, &x0, &precond, eps, max_iter)
+        }),
+    );
+
+    Ok(())
+}
+
+/// Runs the same park in f32, f64 and double-double. Errors are measured
+/// against the exact fractions when the park is small enough, against the
+/// double-double elimination otherwise.
+pub fn compare_precisions(config: &Config) -> Result<(), MatrixError<DoubleDouble>> {
+    let (sparse, b) = Sparse::<DoubleDouble>::from_config(config);
+    let reference = if b.len() <= EXACT_RATIONAL_SIZE {
+        match config.exact_solve() {
+            Ok(exact) => rational::to_f64(&exact),
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(0);
+            }
+        }
+    } else {
+        scalar::to_f64(&sparse.gaussian_partial_pivot(&b)?)
+    };
+
+    println!("Forward error (infinity norm), time and matrix memory:");
+    compare_precision::<f32>("f32", config, &reference);
+    compare_precision::<f64>("f64", config, &reference);
+    compare_precision::<DoubleDouble>("double-double", config, &reference);
+
+    Ok(())
+}
+
+fn compare_precision<T: Scalar>(name: &str, config: &Config, reference: &[f64]) {
+    let (sparse, b) = Sparse::<T>::from_config(config);
+    let x0 = vec![T::ZERO; b.len()];
+    // The stationary step can't shrink below the precision itself.
+    let eps = STATIONARY_TOLERANCE.max(16f64 * T::EPSILON);
+
+    // The sparse keys take the same room in every precision, the dense
+    // rows shrink with the scalar.
+    println!(
+        "\n{} (ε = {:e}, dense {} bytes, sparse {} bytes):",
+        name,
+        T::EPSILON,
+        b.len() * b.len() * std::mem::size_of::<T>(),
+        sparse.entry_bytes()
+    );
+    let print =
+        |solver: &str, (result, elapsed): (Result<Vec<T>, MatrixError<T>>, f64)| match result {
+            Ok(x) => println!(
+                "{}: {:e} in {:.6}ms",
+                solver,
+                Norm::Inf.of_difference(&scalar::to_f64(&x), reference),
+                elapsed
+            ),
+            Err(e) => eprintln!("{}: {}", solver, e),
+        };
+
+    print(
+        "Sparse Gauss (partial pivot)",
+        timed(|| sparse.gaussian_partial_pivot(&b)),
+    );
+    print(
+        "Sparse Gauss-Seidel",
+        timed(|| {
+            sparse
+                .gauss_seidel(&b, &x0, eps, 100_000)
+                .map(|report| report.solution)
+        }),
+    );
+    print(
+        "Sparse BiCGSTAB",
+        timed(|| {
+            sparse
+                .bicgstab(&b, &x0, eps, 10_000)
+                .map(|report| report.solution)
+        }),
+    );
 }
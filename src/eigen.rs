@@ -0,0 +1,376 @@
+use crate::base::*;
+use crate::lu::LuFactorization;
+use crate::matrix::Matrix;
+use crate::sparse::Sparse;
+use crate::Config;
+
+// https://en.wikipedia.org/wiki/Eigenvalue_algorithm
+
+#[derive(Debug, Clone)]
+pub struct Eigenpair {
+    pub value: f64,
+    /// Normalized to ‖v‖₂ = 1.
+    pub vector: Vec<f64>,
+    pub iterations: usize,
+    /// Whether ‖Av - λv‖₂ dropped below eps within max_iter.
+    pub converged: bool,
+}
+
+// https://en.wikipedia.org/wiki/Power_iteration
+//
+// Converges to the eigenvalue of largest modulus, as fast as |λ₂ / λ₁|ᵏ.
+// A dominant pair ±λ, as in every bipartite park, makes it oscillate.
+pub fn power_iteration<F>(apply: F, n: usize, eps: f64, max_iter: usize) -> Eigenpair
+where
+    F: Fn(&[f64]) -> Vec<f64>,
+{
+    let mut x = normalized(&start_vector(n));
+    let mut ax = apply(&x);
+    let mut value = Matrix::dot_product(&x, &ax);
+    let mut iterations = 0;
+
+    while eigen_residual(&ax, value, &x) >= eps && iterations < max_iter {
+        x = normalized(&ax);
+        ax = apply(&x);
+        value = Matrix::dot_product(&x, &ax);
+        iterations += 1;
+    }
+
+    Eigenpair {
+        value,
+        converged: eigen_residual(&ax, value, &x) < eps,
+        vector: x,
+        iterations,
+    }
+}
+
+// https://en.wikipedia.org/wiki/Inverse_iteration
+//
+// Power iteration on (A - σI)⁻¹, whose dominant eigenvalue 1 / (λ - σ)
+// belongs to the eigenvalue λ of A closest to the shift σ. `solve` applies
+// (A - σI)⁻¹, `apply` is A itself and gives the Rayleigh quotient.
+pub fn inverse_iteration<F, S>(
+    apply: F,
+    solve: S,
+    n: usize,
+    eps: f64,
+    max_iter: usize,
+) -> Result<Eigenpair, MatrixError>
+where
+    F: Fn(&[f64]) -> Vec<f64>,
+    S: Fn(&[f64]) -> Result<Vec<f64>, MatrixError>,
+{
+    let mut x = normalized(&start_vector(n));
+    let mut ax = apply(&x);
+    let mut value = Matrix::dot_product(&x, &ax);
+    let mut iterations = 0;
+
+    while eigen_residual(&ax, value, &x) >= eps && iterations < max_iter {
+        x = normalized(&solve(&x)?);
+        ax = apply(&x);
+        value = Matrix::dot_product(&x, &ax);
+        iterations += 1;
+    }
+
+    Ok(Eigenpair {
+        value,
+        converged: eigen_residual(&ax, value, &x) < eps,
+        vector: x,
+        iterations,
+    })
+}
+
+// https://en.wikipedia.org/wiki/Lanczos_algorithm
+//
+// Symmetric matrices only. k steps build a k × k tridiagonal matrix whose
+// eigenvalues, the Ritz values, approximate the extreme eigenvalues of A
+// first. Every new vector is orthogonalized against all the previous
+// ones, plain Lanczos loses orthogonality and repeats eigenvalues.
+// Returns the Ritz values, largest first.
+pub fn lanczos<F>(apply: F, n: usize, k: usize) -> Vec<f64>
+where
+    F: Fn(&[f64]) -> Vec<f64>,
+{
+    let k = k.min(n);
+    let mut basis: Vec<Vec<f64>> = Vec::with_capacity(k);
+    let mut alpha = Vec::with_capacity(k);
+    let mut beta: Vec<f64> = Vec::with_capacity(k);
+    let mut q = normalized(&start_vector(n));
+
+    for step in 0..k {
+        let mut w = apply(&q);
+        alpha.push(Matrix::dot_product(&q, &w));
+        basis.push(q);
+
+        for v in &basis {
+            let projection = Matrix::dot_product(v, &w);
+            for (wi, vi) in w.iter_mut().zip(v) {
+                *wi -= projection * vi;
+            }
+        }
+
+        let w_norm = Matrix::dot_product(&w, &w).sqrt();
+        // An invariant subspace, its Ritz values are exact.
+        if step + 1 == k || w_norm <= 1e-12 * alpha[step].abs().max(1f64) {
+            break;
+        }
+        beta.push(w_norm);
+        q = w.iter().map(|wi| wi / w_norm).collect();
+    }
+
+    let mut values = tridiagonal_eigenvalues(&alpha, &beta);
+    values.reverse();
+    values
+}
+
+// Eigenvalues of the symmetric tridiagonal matrix with diagonal alpha and
+// off-diagonal beta, smallest first, by bisection on Sturm sequences.
+fn tridiagonal_eigenvalues(alpha: &[f64], beta: &[f64]) -> Vec<f64> {
+    let m = alpha.len();
+    let (mut low, mut high) = (f64::INFINITY, f64::NEG_INFINITY);
+    for i in 0..m {
+        let radius =
+            beta.get(i).map_or(0f64, |b| b.abs()) + if i > 0 { beta[i - 1].abs() } else { 0f64 };
+        low = low.min(alpha[i] - radius);
+        high = high.max(alpha[i] + radius);
+    }
+
+    // Number of eigenvalues smaller than x.
+    let count_below = |x: f64| {
+        let mut count = 0;
+        let mut d = 1f64;
+        for i in 0..m {
+            let off = if i > 0 {
+                beta[i - 1] * beta[i - 1]
+            } else {
+                0f64
+            };
+            d = alpha[i] - x - off / d;
+            if d == 0f64 {
+                d = f64::EPSILON * (high - low).abs().max(f64::MIN_POSITIVE);
+            }
+            if d < 0f64 {
+                count += 1;
+            }
+        }
+        count
+    };
+
+    (0..m)
+        .map(|index| {
+            let (mut a, mut b) = (low, high);
+            while b - a > 4f64 * f64::EPSILON * a.abs().max(b.abs()).max(f64::MIN_POSITIVE) {
+                let mid = 0.5 * (a + b);
+                if mid == a || mid == b {
+                    break;
+                }
+                if count_below(mid) > index {
+                    b = mid;
+                } else {
+                    a = mid;
+                }
+            }
+            0.5 * (a + b)
+        })
+        .collect()
+}
+
+// Not a multiple of (1, ..., 1), which is an eigenvector of every
+// transition matrix and would hide the rest of the spectrum.
+fn start_vector(n: usize) -> Vec<f64> {
+    (0..n).map(|i| 1f64 + (i % 7) as f64 / 7f64).collect()
+}
+
+fn normalized(v: &[f64]) -> Vec<f64> {
+    let norm = Matrix::dot_product(v, v).sqrt();
+    v.iter().map(|vi| vi / norm).collect()
+}
+
+fn eigen_residual(ax: &[f64], value: f64, x: &[f64]) -> f64 {
+    ax.iter()
+        .zip(x)
+        .map(|(axi, xi)| (axi - value * xi) * (axi - value * xi))
+        .sum::<f64>()
+        .sqrt()
+}
+
+impl Matrix {
+    pub fn power_iteration(&self, eps: f64, max_iter: usize) -> Eigenpair {
+        let apply = |v: &[f64]| self.multiply_by_vec(v).unwrap();
+        power_iteration(apply, self.rows.len(), eps, max_iter)
+    }
+
+    /// The eigenvalue closest to shift.
+    pub fn inverse_iteration(
+        &self,
+        shift: f64,
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<Eigenpair, MatrixError> {
+        let mut shifted = self.clone();
+        for (i, row) in shifted.rows.iter_mut().enumerate() {
+            row[i] -= shift;
+        }
+        let lu = LuFactorization::from_matrix(&shifted)?;
+
+        let apply = |v: &[f64]| self.multiply_by_vec(v).unwrap();
+        inverse_iteration(apply, |v| lu.solve(v), self.rows.len(), eps, max_iter)
+    }
+
+    pub fn lanczos(&self, k: usize) -> Vec<f64> {
+        let apply = |v: &[f64]| self.multiply_by_vec(v).unwrap();
+        lanczos(apply, self.rows.len(), k)
+    }
+}
+
+impl Sparse {
+    pub fn power_iteration(&self, eps: f64, max_iter: usize) -> Eigenpair {
+        power_iteration(|v| self.multiply_by_vec(v), self.size(), eps, max_iter)
+    }
+
+    /// The eigenvalue closest to shift.
+    pub fn inverse_iteration(
+        &self,
+        shift: f64,
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<Eigenpair, MatrixError> {
+        let n = self.size();
+        let mut shifted = self.clone();
+        for i in 0..n {
+            shifted.set_value(i, i, self.get_value(i, i) - shift);
+        }
+        let lu = LuFactorization::from_sparse(&shifted)?;
+
+        inverse_iteration(
+            |v| self.multiply_by_vec(v),
+            |v| lu.solve(v),
+            n,
+            eps,
+            max_iter,
+        )
+    }
+
+    pub fn lanczos(&self, k: usize) -> Vec<f64> {
+        lanczos(|v| self.multiply_by_vec(v), self.size(), k)
+    }
+}
+
+/// Where the spectrum of a park's random walk puts its mixing time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParkSpectrum {
+    /// Largest eigenvalue of the transition matrix below the eigenvalue 1
+    /// of the exits and wells. It is the largest eigenvalue of the block Q
+    /// between the interior intersections, which is the Jacobi iteration
+    /// matrix of from_config, so it is also Jacobi's convergence rate.
+    pub second_eigenvalue: f64,
+    /// Smallest eigenvalue of Q. Close to -λ₂ when the park is (almost)
+    /// bipartite, a walk that alternates between two halves of the park.
+    pub smallest_eigenvalue: f64,
+    /// 1 / (1 - λ₂), the number of steps it takes the walk to forget
+    /// where it started, up to a log factor.
+    pub relaxation_time: f64,
+}
+
+impl Config {
+    /// Transition probabilities of the walk, proportional to 1 / length
+    /// over the alleys of an intersection. Exits and wells keep the walker.
+    pub fn transition_matrix(&self) -> Sparse {
+        let n = self.inters.len();
+        let degrees = self.conductance_degrees::<f64>();
+        let mut out = Sparse::from_size(n);
+
+        for (i, inter) in self.inters.iter().enumerate() {
+            if inter.exit || inter.well {
+                out.set_value(i, i, 1f64);
+            }
+        }
+        for (i, j, weight) in self.conductances::<f64>() {
+            if !self.inters[i].exit && !self.inters[i].well {
+                out.set_value(i, j, out.get_value(i, j) + weight / degrees[i]);
+            }
+        }
+
+        out
+    }
+
+    /// Spectrum of the walk from k Lanczos steps. Q isn't symmetric, but
+    /// the walk is reversible, so D^½ Q D^-½ with D the sums of 1 / length
+    /// at every intersection is, and has the same eigenvalues.
+    pub fn spectrum(&self, k: usize) -> ParkSpectrum {
+        let degrees = self.conductance_degrees::<f64>();
+        let interior = self.interior();
+        let mut position = vec![None; self.inters.len()];
+        for (p, &i) in interior.iter().enumerate() {
+            position[i] = Some(p);
+        }
+
+        let mut symmetric = Sparse::from_size(interior.len());
+        for (i, j, weight) in self.conductances::<f64>() {
+            if let (Some(p), Some(q)) = (position[i], position[j]) {
+                let weight = weight / (degrees[i] * degrees[j]).sqrt();
+                symmetric.set_value(p, q, symmetric.get_value(p, q) + weight);
+            }
+        }
+
+        let values = lanczos(|v| symmetric.multiply_by_vec(v), interior.len(), k);
+        let second_eigenvalue = values.first().copied().unwrap_or(0f64);
+
+        ParkSpectrum {
+            second_eigenvalue,
+            smallest_eigenvalue: values.last().copied().unwrap_or(0f64),
+            relaxation_time: 1f64 / (1f64 - second_eigenvalue),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::path_park;
+
+    #[test]
+    fn test_eigen_solvers() {
+        // Eigenvalues 3 - √3, 3 and 3 + √3.
+        let a = Matrix::from_vecs(vec![
+            vec![2.0, -1.0, 0.0],
+            vec![-1.0, 3.0, -1.0],
+            vec![0.0, -1.0, 4.0],
+        ]);
+        let root3 = 3f64.sqrt();
+
+        let dominant = a.power_iteration(1e-10, 1_000);
+        assert!(dominant.converged);
+        assert!((dominant.value - (3.0 + root3)).abs() < 1e-9);
+
+        let sparse = Sparse::from_vecs(a.rows.clone());
+        let nearest = sparse.inverse_iteration(1.0, 1e-12, 100).unwrap();
+        assert!((nearest.value - (3.0 - root3)).abs() < 1e-12);
+        let middle = a.inverse_iteration(2.9, 1e-12, 100).unwrap();
+        assert!((middle.value - 3.0).abs() < 1e-12);
+
+        let ritz = sparse.lanczos(3);
+        for (value, expected) in ritz.iter().zip([3.0 + root3, 3.0, 3.0 - root3]) {
+            assert!((value - expected).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_path_spectrum() {
+        // Q is the walk on the 3 intersections between the exits, with
+        // eigenvalues cos(kπ / 4).
+        let path = path_park();
+        let spectrum = path.spectrum(10);
+        let rho = (std::f64::consts::PI / 4.0).cos();
+        assert!((spectrum.second_eigenvalue - rho).abs() < 1e-12);
+        assert!((spectrum.smallest_eigenvalue + rho).abs() < 1e-12);
+
+        let (a, _) = Sparse::<f64>::from_config(&path);
+        assert!((a.jacobi_spectral_radius(10_000) - rho).abs() < 1e-6);
+
+        let p = path.transition_matrix();
+        assert_eq!(p.get_value(2, 1), 0.5);
+        let dominant = p.power_iteration(1e-12, 10_000);
+        assert!((dominant.value - 1.0).abs() < 1e-9);
+    }
+}
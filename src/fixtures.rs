@@ -0,0 +1,45 @@
+// Parks shared by the tests of several modules.
+
+use crate::{Config, Sets};
+
+/// A square grid of side × side intersections joined to their right and
+/// lower neighbours. The left column is all exits, the right one all
+/// wells, and the walk starts in the middle.
+pub fn grid_park(side: usize) -> Config {
+    let id = |r: usize, c: usize| r * side + c + 1;
+    let mut alleys = vec![vec![side * side, 2 * side * (side - 1)]];
+    for r in 0..side {
+        for c in 0..side {
+            let length = (r + 2 * c) % 4 + 1;
+            if c + 1 < side {
+                alleys.push(vec![id(r, c), id(r, c + 1), length]);
+            }
+            if r + 1 < side {
+                alleys.push(vec![id(r, c), id(r + 1, c), length]);
+            }
+        }
+    }
+    let mut wells = vec![side];
+    wells.extend((0..side).map(|r| id(r, side - 1)));
+    let mut exits = vec![side];
+    exits.extend((0..side).map(|r| id(r, 0)));
+    Config::build(Sets(vec![
+        alleys,
+        vec![wells, exits, vec![1, id(side / 2, side / 2)]],
+    ]))
+}
+
+/// Exit 1 - 2 - 3 - 4 - 5 exit with alleys of length 1, a symmetric walk
+/// that starts at 3.
+pub fn path_park() -> Config {
+    Config::build(Sets(vec![
+        vec![
+            vec![5, 4],
+            vec![1, 2, 1],
+            vec![2, 3, 1],
+            vec![3, 4, 1],
+            vec![4, 5, 1],
+        ],
+        vec![vec![0], vec![2, 1, 5], vec![1, 3]],
+    ]))
+}
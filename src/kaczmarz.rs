@@ -0,0 +1,194 @@
+use rand::distributions::WeightedIndex;
+use rand::prelude::*;
+
+use crate::base::*;
+use crate::matrix::Matrix;
+use crate::scalar::Scalar;
+use crate::sparse::Sparse;
+use crate::stopping::{Monitor, StoppingCriterion, StoppingTest};
+
+// https://en.wikipedia.org/wiki/Kaczmarz_method
+//
+// Every step projects x onto the hyperplane of one row, a_i·x = b_i. This
+// is Gauss-Seidel on AAᵀy = b with x = Aᵀy, so it converges for every
+// consistent system, diagonally dominant or not, only more slowly.
+// The randomized method picks row i with probability ‖a_i‖² / ‖A‖²_F,
+// which converges in expectation at a rate set by the condition number
+// of A instead of by the order of the rows. One iteration is n projections
+// for both, so their iteration counts compare to the other solvers'.
+// The randomized sweeps can leave x where it was by hitting the same row
+// twice, so kaczmarz stops on the residual rather than on the step.
+
+// Seeds the row sampler, so the randomized runs and their timings can be
+// reproduced.
+const RANDOMIZED_SEED: u64 = 0x5eed;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RowOrder {
+    Cyclic,
+    Randomized,
+}
+
+impl<T: Scalar> Matrix<T> {
+    pub fn kaczmarz(
+        &self,
+        b: &[T],
+        x0: &[T],
+        order: RowOrder,
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        let stop = StoppingCriterion::new(StoppingTest::AbsoluteResidual(eps), max_iter);
+        self.kaczmarz_until(b, x0, order, &stop)
+    }
+
+    pub fn kaczmarz_until(
+        &self,
+        b: &[T],
+        x0: &[T],
+        order: RowOrder,
+        stop: &StoppingCriterion,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        let rows: Vec<Vec<(usize, T)>> = self
+            .rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .enumerate()
+                    .filter(|(_, &val)| val != T::ZERO)
+                    .map(|(j, &val)| (j, val))
+                    .collect()
+            })
+            .collect();
+        row_action(&rows, b, x0, order, stop)
+    }
+}
+
+impl<T: Scalar> Sparse<T> {
+    pub fn kaczmarz(
+        &self,
+        b: &[T],
+        x0: &[T],
+        order: RowOrder,
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        let stop = StoppingCriterion::new(StoppingTest::AbsoluteResidual(eps), max_iter);
+        self.kaczmarz_until(b, x0, order, &stop)
+    }
+
+    pub fn kaczmarz_until(
+        &self,
+        b: &[T],
+        x0: &[T],
+        order: RowOrder,
+        stop: &StoppingCriterion,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        row_action(&self.rows(b.len()), b, x0, order, stop)
+    }
+}
+
+fn row_action<T: Scalar>(
+    rows: &[Vec<(usize, T)>],
+    b: &[T],
+    x0: &[T],
+    order: RowOrder,
+    stop: &StoppingCriterion,
+) -> Result<SolveReport<T>, MatrixError<T>> {
+    if rows.len() != b.len() {
+        return Err(MatrixError::SizeError);
+    }
+
+    let norms: Vec<T> = rows
+        .iter()
+        .map(|row| row.iter().map(|&(_, val)| val * val).sum())
+        .collect();
+    // A zero row only fits a zero right-hand side, and then says nothing.
+    if norms
+        .iter()
+        .zip(b)
+        .any(|(&norm, &bi)| norm == T::ZERO && bi != T::ZERO)
+    {
+        return Err(MatrixError::Unsolvable);
+    }
+    let sampler = match order {
+        RowOrder::Cyclic => None,
+        RowOrder::Randomized => Some(
+            WeightedIndex::new(norms.iter().map(|norm| norm.to_f64()))
+                .map_err(|_| MatrixError::Unsolvable)?,
+        ),
+    };
+
+    let mul = |v: &[T]| {
+        rows.iter()
+            .map(|row| row.iter().map(|&(j, val)| val * v[j]).sum())
+            .collect::<Vec<T>>()
+    };
+    let mut rng = StdRng::seed_from_u64(RANDOMIZED_SEED);
+    let mut monitor = Monitor::new(stop, b);
+    let mut x = x0.to_vec();
+
+    while monitor.running() {
+        let prev = x.clone();
+        for k in 0..rows.len() {
+            let i = match &sampler {
+                Some(sampler) => sampler.sample(&mut rng),
+                None => k,
+            };
+            if norms[i] == T::ZERO {
+                continue;
+            }
+
+            let ax: T = rows[i].iter().map(|&(j, val)| val * x[j]).sum();
+            let step = (b[i] - ax) / norms[i];
+            for &(j, val) in &rows[i] {
+                x[j] += step * val;
+            }
+        }
+        monitor.update(&prev, &x, mul);
+    }
+
+    let ax = mul(&x);
+    monitor.finish(x, &ax)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Config;
+
+    #[test]
+    fn test_kaczmarz_without_dominance() {
+        // Jacobi's iteration matrix has spectral radius √6 here.
+        let a = Matrix::from_vecs(vec![vec![1.0, 3.0], vec![2.0, 1.0]]);
+        let b = vec![7.0, 4.0];
+        let x0 = vec![0f64; 2];
+        assert!(a.jacobi(&b, &x0, 1e-12, 1_000).is_err());
+
+        for order in [RowOrder::Cyclic, RowOrder::Randomized] {
+            let report = a.kaczmarz(&b, &x0, order, 1e-13, 10_000).unwrap();
+            assert!(crate::comparisons::compare_vecs(
+                &report.solution,
+                &vec![1.0, 2.0],
+                1e-10
+            ));
+        }
+    }
+
+    #[test]
+    fn test_sparse_kaczmarz() {
+        let cfg = Config::build(crate::parse_config("default.config"));
+        let (sparse, b) = Sparse::from_config(&cfg);
+        let expected = sparse.gaussian_partial_pivot(&b).unwrap();
+        let x0 = vec![0f64; b.len()];
+
+        for order in [RowOrder::Cyclic, RowOrder::Randomized] {
+            let report = sparse.kaczmarz(&b, &x0, order, 1e-13, 10_000).unwrap();
+            assert!(crate::comparisons::compare_vecs(
+                &report.solution,
+                &expected,
+                1e-10
+            ));
+        }
+    }
+}
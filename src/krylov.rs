@@ -0,0 +1,316 @@
+use crate::base::*;
+use crate::matrix::Matrix;
+use crate::operator::LinearOperator;
+use crate::preconditioner::Preconditioner;
+use crate::scalar::Scalar;
+
+// https://en.wikipedia.org/wiki/Conjugate_gradient_method
+//
+// Only converges for symmetric positive definite systems, so the park
+// has to be built with from_config_symmetric rather than from_config.
+// The preconditioner has to be symmetric positive definite as well.
+pub fn conjugate_gradient<T: Scalar, A>(
+    a: &A,
+    b: &[T],
+    x0: &[T],
+    precond: &dyn Preconditioner<T>,
+    eps: f64,
+    max_iter: usize,
+) -> Result<SolveReport<T>, MatrixError<T>>
+where
+    A: LinearOperator<T> + ?Sized,
+{
+    let mut x = x0.to_vec();
+    let mut r = residual(a, b, &x);
+    let mut z = precond.apply(&r);
+    let mut p = z.clone();
+    let mut rz_old = Matrix::<T>::dot_product(&r, &z);
+    let mut history = Vec::new();
+    let mut iterations = 0;
+
+    loop {
+        history.push(norm(&r));
+        if norm(&r) < eps || iterations == max_iter {
+            break;
+        }
+        iterations += 1;
+
+        let ap = a.apply(&p);
+        let alpha = rz_old / Matrix::<T>::dot_product(&p, &ap);
+        for (xi, &pi) in x.iter_mut().zip(&p) {
+            *xi += alpha * pi;
+        }
+        for (ri, &api) in r.iter_mut().zip(&ap) {
+            *ri -= alpha * api;
+        }
+
+        z = precond.apply(&r);
+        let rz_new = Matrix::<T>::dot_product(&r, &z);
+        let beta = rz_new / rz_old;
+        for (pi, &zi) in p.iter_mut().zip(&z) {
+            *pi = zi + beta * *pi;
+        }
+        rz_old = rz_new;
+    }
+
+    finish(a, b, x, iterations, eps, history).into_result()
+}
+
+// https://en.wikipedia.org/wiki/Generalized_minimal_residual_method
+//
+// Restarted GMRES(m): the Krylov basis is thrown away every `restart`
+// steps to keep memory at O(restart * n). Every Arnoldi step counts
+// as one iteration towards max_iter. Preconditioned from the right,
+// so the residual it minimizes is still the one of the original system.
+pub fn gmres<T: Scalar, A>(
+    a: &A,
+    b: &[T],
+    x0: &[T],
+    precond: &dyn Preconditioner<T>,
+    restart: usize,
+    eps: f64,
+    max_iter: usize,
+) -> Result<SolveReport<T>, MatrixError<T>>
+where
+    A: LinearOperator<T> + ?Sized,
+{
+    let restart = restart.clamp(1, b.len().max(1));
+    let mut x = x0.to_vec();
+    let mut it = 0;
+    let mut history = Vec::new();
+
+    loop {
+        let r = residual(a, b, &x);
+        let beta = Matrix::<T>::dot_product(&r, &r).sqrt();
+        // After a restart this is the iterate the last |g[k]| belongs to,
+        // recomputed without the rounding of the Givens updates.
+        match history.last_mut() {
+            Some(last) => *last = beta.to_f64(),
+            None => history.push(beta.to_f64()),
+        }
+        if beta.to_f64() < eps {
+            break;
+        }
+        if it >= max_iter {
+            break;
+        }
+
+        let mut basis = vec![r.iter().map(|&ri| ri / beta).collect::<Vec<T>>()];
+        let mut h = vec![vec![T::ZERO; restart]; restart + 1];
+        let mut cs = vec![T::ZERO; restart];
+        let mut sn = vec![T::ZERO; restart];
+        let mut g = vec![T::ZERO; restart + 1];
+        g[0] = beta;
+
+        let mut k = 0;
+        while k < restart && it < max_iter {
+            let mut w = a.apply(&precond.apply(&basis[k]));
+            for j in 0..=k {
+                h[j][k] = Matrix::<T>::dot_product(&w, &basis[j]);
+                for (wi, &vi) in w.iter_mut().zip(&basis[j]) {
+                    *wi -= h[j][k] * vi;
+                }
+            }
+            let h_next = Matrix::<T>::dot_product(&w, &w).sqrt();
+            h[k + 1][k] = h_next;
+
+            for j in 0..k {
+                let tmp = cs[j] * h[j][k] + sn[j] * h[j + 1][k];
+                h[j + 1][k] = -sn[j] * h[j][k] + cs[j] * h[j + 1][k];
+                h[j][k] = tmp;
+            }
+
+            let denom = h[k][k].hypot(h[k + 1][k]);
+            if denom == T::ZERO {
+                break;
+            }
+            cs[k] = h[k][k] / denom;
+            sn[k] = h[k + 1][k] / denom;
+            h[k][k] = denom;
+            h[k + 1][k] = T::ZERO;
+            g[k + 1] = -sn[k] * g[k];
+            g[k] *= cs[k];
+
+            it += 1;
+            k += 1;
+
+            // |g[k]| is the residual norm of the current iterate, for free.
+            history.push(g[k].abs().to_f64());
+            if g[k].abs().to_f64() < eps || h_next == T::ZERO {
+                break;
+            }
+            basis.push(w.iter().map(|&wi| wi / h_next).collect());
+        }
+
+        if k == 0 {
+            break;
+        }
+
+        let mut y = vec![T::ZERO; k];
+        for i in (0..k).rev() {
+            y[i] = g[i];
+            for j in (i + 1)..k {
+                let update = h[i][j] * y[j];
+                y[i] -= update;
+            }
+            y[i] /= h[i][i];
+        }
+
+        let mut update = vec![T::ZERO; x.len()];
+        for (&yj, vj) in y.iter().zip(&basis) {
+            for (ui, &vi) in update.iter_mut().zip(vj) {
+                *ui += yj * vi;
+            }
+        }
+        for (xi, zi) in x.iter_mut().zip(precond.apply(&update)) {
+            *xi += zi;
+        }
+    }
+
+    finish(a, b, x, it, eps, history).into_result()
+}
+
+// https://en.wikipedia.org/wiki/Biconjugate_gradient_stabilized_method
+//
+// Preconditioned from the right, like gmres.
+pub fn bicgstab<T: Scalar, A>(
+    a: &A,
+    b: &[T],
+    x0: &[T],
+    precond: &dyn Preconditioner<T>,
+    eps: f64,
+    max_iter: usize,
+) -> Result<SolveReport<T>, MatrixError<T>>
+where
+    A: LinearOperator<T> + ?Sized,
+{
+    let n = b.len();
+    let mut x = x0.to_vec();
+    let mut r = residual(a, b, &x);
+    let mut r_hat = r.clone();
+    let mut rho = T::ONE;
+    let mut alpha = T::ONE;
+    let mut omega = T::ONE;
+    let mut v = vec![T::ZERO; n];
+    let mut p = vec![T::ZERO; n];
+    let mut history = Vec::new();
+    let mut iterations = 0;
+
+    loop {
+        history.push(norm(&r));
+        if norm(&r) < eps || iterations == max_iter {
+            break;
+        }
+        iterations += 1;
+
+        let mut rho_new = Matrix::<T>::dot_product(&r_hat, &r);
+        if rho_new == T::ZERO {
+            // The shadow residual became orthogonal to r, which happens
+            // on parks where b is nonzero only on the identity rows of
+            // the exits. Restart with the current residual as the shadow.
+            r_hat = r.clone();
+            rho = T::ONE;
+            alpha = T::ONE;
+            omega = T::ONE;
+            v = vec![T::ZERO; n];
+            p = vec![T::ZERO; n];
+            rho_new = Matrix::<T>::dot_product(&r_hat, &r);
+        }
+
+        let beta = (rho_new / rho) * (alpha / omega);
+        for i in 0..n {
+            p[i] = r[i] + beta * (p[i] - omega * v[i]);
+        }
+
+        let p_hat = precond.apply(&p);
+        v = a.apply(&p_hat);
+        alpha = rho_new / Matrix::<T>::dot_product(&r_hat, &v);
+        let s: Vec<T> = r.iter().zip(&v).map(|(&ri, &vi)| ri - alpha * vi).collect();
+        for (xi, &pi) in x.iter_mut().zip(&p_hat) {
+            *xi += alpha * pi;
+        }
+        if norm(&s) < eps {
+            r = s;
+            continue;
+        }
+
+        let s_hat = precond.apply(&s);
+        let t = a.apply(&s_hat);
+        let tt = Matrix::<T>::dot_product(&t, &t);
+        if tt == T::ZERO {
+            break;
+        }
+        omega = Matrix::<T>::dot_product(&t, &s) / tt;
+        for (xi, &si) in x.iter_mut().zip(&s_hat) {
+            *xi += omega * si;
+        }
+        r = s.iter().zip(&t).map(|(&si, &ti)| si - omega * ti).collect();
+        rho = rho_new;
+    }
+
+    finish(a, b, x, iterations, eps, history).into_result()
+}
+
+// https://en.wikipedia.org/wiki/Modified_Richardson_iteration
+//
+// The stationary iteration x += M⁻¹(b - Ax). With the Jacobi
+// preconditioner this is the Jacobi method, with ILU(0) it converges
+// much faster on the park systems.
+pub fn richardson<T: Scalar, A>(
+    a: &A,
+    b: &[T],
+    x0: &[T],
+    precond: &dyn Preconditioner<T>,
+    eps: f64,
+    max_iter: usize,
+) -> Result<SolveReport<T>, MatrixError<T>>
+where
+    A: LinearOperator<T> + ?Sized,
+{
+    let mut x = x0.to_vec();
+    let mut history = Vec::new();
+    let mut iterations = 0;
+
+    loop {
+        let r = residual(a, b, &x);
+        history.push(norm(&r));
+        if norm(&r) < eps || iterations == max_iter {
+            break;
+        }
+        iterations += 1;
+
+        for (xi, zi) in x.iter_mut().zip(precond.apply(&r)) {
+            *xi += zi;
+        }
+    }
+
+    finish(a, b, x, iterations, eps, history).into_result()
+}
+
+// Report with the true residual of x, the recurrences of CG and BiCGSTAB
+// drift away from it in floating point.
+fn finish<T: Scalar, A>(
+    a: &A,
+    b: &[T],
+    x: Vec<T>,
+    iterations: usize,
+    eps: f64,
+    history: Vec<f64>,
+) -> SolveReport<T>
+where
+    A: LinearOperator<T> + ?Sized,
+{
+    let converged = history.last().is_some_and(|&last| last < eps);
+    SolveReport::new(b, &a.apply(&x), x, iterations, converged).with_history(history)
+}
+
+fn residual<T: Scalar, A>(a: &A, b: &[T], x: &[T]) -> Vec<T>
+where
+    A: LinearOperator<T> + ?Sized,
+{
+    b.iter().zip(a.apply(x)).map(|(&bi, ax)| bi - ax).collect()
+}
+
+fn norm<T: Scalar>(v: &[T]) -> f64 {
+    Matrix::<T>::dot_product(v, v).sqrt().to_f64()
+}
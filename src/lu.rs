@@ -0,0 +1,373 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::base::*;
+use crate::matrix::Matrix;
+use crate::scalar::Scalar;
+use crate::sparse::Sparse;
+
+/// LU factorization with partial pivoting, PA = LU.
+///
+/// Factoring costs as much as one Gaussian elimination, after that every
+/// right-hand side is just a forward and a backward substitution.
+#[derive(Clone, Debug)]
+pub struct LuFactorization<T: Scalar = f64> {
+    // Row k of L without its unit diagonal, as (column, value) pairs.
+    lower: Vec<Vec<(usize, T)>>,
+    // Row k of U starting at the diagonal, as (column, value) pairs.
+    upper: Vec<Vec<(usize, T)>>,
+    // perm[k] is the row of the original matrix that ended up as row k.
+    perm: Vec<usize>,
+}
+
+impl<T: Scalar> LuFactorization<T> {
+    pub fn from_matrix(a: &Matrix<T>) -> Result<Self, MatrixError<T>> {
+        let n = a.rows.len();
+        if a.rows.iter().any(|row| row.len() != n) {
+            return Err(MatrixError::SizeError);
+        }
+
+        let mut lu = a.rows.clone();
+        let mut perm: Vec<usize> = (0..n).collect();
+
+        for k in 0..n {
+            let mut max_row = k;
+            for i in (k + 1)..n {
+                if lu[i][k].abs() > lu[max_row][k].abs() {
+                    max_row = i;
+                }
+            }
+
+            if lu[max_row][k] == T::ZERO {
+                return Err(MatrixError::Unsolvable);
+            }
+
+            if max_row != k {
+                lu.swap(k, max_row);
+                perm.swap(k, max_row);
+            }
+
+            let (top, bottom) = lu.split_at_mut(k + 1);
+            let pivot_row = &top[k];
+            for row in bottom {
+                let factor = row[k] / pivot_row[k];
+                row[k] = factor;
+                if factor == T::ZERO {
+                    continue;
+                }
+                for (val, &pivot_val) in row[k + 1..].iter_mut().zip(&pivot_row[k + 1..]) {
+                    *val -= factor * pivot_val;
+                }
+            }
+        }
+
+        let lower = lu
+            .iter()
+            .enumerate()
+            .map(|(k, row)| {
+                (0..k)
+                    .filter(|&j| row[j] != T::ZERO)
+                    .map(|j| (j, row[j]))
+                    .collect()
+            })
+            .collect();
+        let upper = lu
+            .iter()
+            .enumerate()
+            .map(|(k, row)| {
+                (k..n)
+                    .filter(|&j| j == k || row[j] != T::ZERO)
+                    .map(|j| (j, row[j]))
+                    .collect()
+            })
+            .collect();
+
+        Ok(Self { lower, upper, perm })
+    }
+
+    /// Eliminates on the sparsity pattern of the matrix, so only the
+    /// fill-in is ever stored on top of the original entries.
+    pub fn from_sparse(a: &Sparse<T>) -> Result<Self, MatrixError<T>> {
+        let n = a.size();
+        let mut rows: Vec<BTreeMap<usize, T>> = a
+            .rows(n)
+            .into_iter()
+            .map(|row| row.into_iter().collect())
+            .collect();
+
+        // cols[j] holds the not yet pivoted rows with an entry in column j.
+        let mut cols = vec![BTreeSet::new(); n];
+        for (i, row) in rows.iter().enumerate() {
+            for &j in row.keys() {
+                cols[j].insert(i);
+            }
+        }
+
+        let mut lower_by_row: Vec<Vec<(usize, T)>> = vec![Vec::new(); n];
+        let mut upper = Vec::with_capacity(n);
+        let mut perm = Vec::with_capacity(n);
+
+        for k in 0..n {
+            let candidates = std::mem::take(&mut cols[k]);
+            let pivot_row = match candidates
+                .iter()
+                .copied()
+                .max_by(|&p, &q| rows[p][&k].abs().total_cmp(&rows[q][&k].abs()))
+            {
+                Some(p) if rows[p][&k] != T::ZERO => p,
+                _ => return Err(MatrixError::Unsolvable),
+            };
+
+            let pivot_entries: Vec<(usize, T)> =
+                std::mem::take(&mut rows[pivot_row]).into_iter().collect();
+            for &(j, _) in &pivot_entries {
+                cols[j].remove(&pivot_row);
+            }
+            let pivot = pivot_entries[0].1;
+
+            for &i in candidates.iter().filter(|&&i| i != pivot_row) {
+                let factor = rows[i].remove(&k).unwrap_or(T::ZERO) / pivot;
+                lower_by_row[i].push((k, factor));
+                for &(j, val) in &pivot_entries[1..] {
+                    let entry = rows[i].entry(j).or_insert_with(|| {
+                        cols[j].insert(i);
+                        T::ZERO
+                    });
+                    *entry -= factor * val;
+                }
+            }
+
+            upper.push(pivot_entries);
+            perm.push(pivot_row);
+        }
+
+        let lower = perm
+            .iter()
+            .map(|&row| std::mem::take(&mut lower_by_row[row]))
+            .collect();
+
+        Ok(Self { lower, upper, perm })
+    }
+
+    pub fn size(&self) -> usize {
+        self.perm.len()
+    }
+
+    pub fn permutation(&self) -> &[usize] {
+        &self.perm
+    }
+
+    pub fn solve(&self, b: &[T]) -> Result<Vec<T>, MatrixError<T>> {
+        let n = self.size();
+        if b.len() != n {
+            return Err(MatrixError::SizeError);
+        }
+
+        let mut y = vec![T::ZERO; n];
+        for k in 0..n {
+            y[k] = b[self.perm[k]];
+            for &(j, val) in &self.lower[k] {
+                let update = val * y[j];
+                y[k] -= update;
+            }
+        }
+
+        let mut x = vec![T::ZERO; n];
+        for k in (0..n).rev() {
+            x[k] = y[k];
+            for &(j, val) in &self.upper[k][1..] {
+                let update = val * x[j];
+                x[k] -= update;
+            }
+            x[k] /= self.upper[k][0].1;
+        }
+
+        Ok(x)
+    }
+
+    /// Solves Aᵀx = b with the same factors. PA = LU gives Aᵀ = UᵀLᵀP,
+    /// U and L are stored by rows, so both are applied column by column.
+    pub fn solve_transposed(&self, b: &[T]) -> Result<Vec<T>, MatrixError<T>> {
+        let n = self.size();
+        if b.len() != n {
+            return Err(MatrixError::SizeError);
+        }
+
+        let mut z = b.to_vec();
+        for k in 0..n {
+            z[k] /= self.upper[k][0].1;
+            for &(j, val) in &self.upper[k][1..] {
+                let update = val * z[k];
+                z[j] -= update;
+            }
+        }
+
+        for k in (0..n).rev() {
+            for &(j, val) in &self.lower[k] {
+                let update = val * z[k];
+                z[j] -= update;
+            }
+        }
+
+        let mut x = vec![T::ZERO; n];
+        for (k, &row) in self.perm.iter().enumerate() {
+            x[row] = z[k];
+        }
+
+        Ok(x)
+    }
+
+    pub fn solve_many(&self, bs: &[Vec<T>]) -> Result<Vec<Vec<T>>, MatrixError<T>> {
+        bs.iter().map(|b| self.solve(b)).collect()
+    }
+
+    /// det(A) = sign(P) · u_00 · u_11 ⋯ u_(n-1)(n-1).
+    pub fn determinant(&self) -> T {
+        let product: T = self.upper.iter().map(|row| row[0].1).product();
+
+        // A permutation is odd when n minus its number of cycles is.
+        let n = self.size();
+        let mut visited = vec![false; n];
+        let mut cycles = 0;
+        for start in 0..n {
+            if visited[start] {
+                continue;
+            }
+            cycles += 1;
+            let mut k = start;
+            while !visited[k] {
+                visited[k] = true;
+                k = self.perm[k];
+            }
+        }
+
+        if (n - cycles) % 2 == 1 {
+            -product
+        } else {
+            product
+        }
+    }
+
+    /// A⁻¹, one solve per column of the identity.
+    pub fn inverse(&self) -> Result<Matrix<T>, MatrixError<T>> {
+        let n = self.size();
+        let mut rows = vec![vec![T::ZERO; n]; n];
+
+        for j in 0..n {
+            let mut e = vec![T::ZERO; n];
+            e[j] = T::ONE;
+            for (row, val) in rows.iter_mut().zip(self.solve(&e)?) {
+                row[j] = val;
+            }
+        }
+
+        Ok(Matrix::from_vecs(rows))
+    }
+}
+
+impl<T: Scalar> Matrix<T> {
+    /// Zero for a singular matrix, the factorization stops at the first
+    /// column without a nonzero pivot.
+    pub fn determinant(&self) -> Result<T, MatrixError<T>> {
+        match LuFactorization::from_matrix(self) {
+            Ok(lu) => Ok(lu.determinant()),
+            Err(MatrixError::Unsolvable) => Ok(T::ZERO),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn inverse(&self) -> Result<Matrix<T>, MatrixError<T>> {
+        LuFactorization::from_matrix(self)?.inverse()
+    }
+}
+
+impl<T: Scalar> Sparse<T> {
+    pub fn determinant(&self) -> Result<T, MatrixError<T>> {
+        match LuFactorization::from_sparse(self) {
+            Ok(lu) => Ok(lu.determinant()),
+            Err(MatrixError::Unsolvable) => Ok(T::ZERO),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comparisons::compare_vecs;
+    use crate::fixtures::path_park;
+    use crate::Config;
+
+    #[test]
+    fn test_lu_solve() {
+        let a = Matrix::from_vecs(vec![
+            vec![0.0, 2.0, 1.0],
+            vec![1.0, 1.0, 1.0],
+            vec![4.0, 3.0, 0.0],
+        ]);
+        let lu = LuFactorization::from_matrix(&a).unwrap();
+        assert_eq!(lu.permutation(), &[2, 0, 1]);
+
+        let bs = vec![vec![1.0, 2.0, 3.0], vec![0.0, 1.0, 0.0]];
+        for (b, x) in bs.iter().zip(lu.solve_many(&bs).unwrap()) {
+            assert!(compare_vecs(&a.multiply_by_vec(&x).unwrap(), b, 1e-12));
+        }
+    }
+
+    #[test]
+    fn test_sparse_lu_solve() {
+        let cfg = Config::build(crate::parse_config("default.config"));
+        let (a, b) = Sparse::from_config(&cfg);
+        let lu = LuFactorization::from_sparse(&a).unwrap();
+
+        let x = lu.solve(&b).unwrap();
+        assert!(compare_vecs(&a.multiply_by_vec(&x), &b, 1e-12));
+        assert!(lu.solve(&[1.0]).is_err());
+    }
+
+    #[test]
+    fn test_determinant_inverse() {
+        let a = Matrix::from_vecs(vec![
+            vec![0.0, 2.0, 1.0],
+            vec![1.0, 1.0, 1.0],
+            vec![4.0, 3.0, 0.0],
+        ]);
+        assert!((a.determinant().unwrap() - 7.0).abs() < 1e-12);
+        assert!((Sparse::from_vecs(a.rows.clone()).determinant().unwrap() - 7.0).abs() < 1e-12);
+
+        let swapped = Matrix::from_vecs(vec![
+            a.rows[1].clone(),
+            a.rows[0].clone(),
+            a.rows[2].clone(),
+        ]);
+        assert!((swapped.determinant().unwrap() + 7.0).abs() < 1e-12);
+        let singular = Matrix::from_vecs(vec![vec![1.0, 2.0], vec![2.0, 4.0]]);
+        assert_eq!(singular.determinant().unwrap(), 0.0);
+        assert!(singular.inverse().is_err());
+
+        let product = a.multiply(&a.inverse().unwrap()).unwrap();
+        for (i, row) in product.rows.iter().enumerate() {
+            let mut e = vec![0f64; 3];
+            e[i] = 1f64;
+            assert!(compare_vecs(row, &e, 1e-12));
+        }
+    }
+
+    #[test]
+    fn test_fundamental_matrix() {
+        let path = path_park();
+        let fundamental = path.fundamental_matrix().unwrap();
+
+        // N[k][l] = 2 min(i, j) (4 - max(i, j)) / 4 for intersections i, j.
+        assert!(compare_vecs(
+            &fundamental.rows[0],
+            &vec![1.5, 1.0, 0.5],
+            1e-12
+        ));
+        let steps: Vec<f64> = fundamental
+            .rows
+            .iter()
+            .map(|row| row.iter().sum())
+            .collect();
+        assert!(compare_vecs(&steps, &vec![3.0, 4.0, 3.0], 1e-12));
+    }
+}
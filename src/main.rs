@@ -2,16 +2,39 @@ use std::io::{BufRead, BufReader};
 use std::process::Command;
 use std::{env, fs, process};
 
+use banded::Banded;
 use base::*;
 use comparisons::incremental_verify_mc;
 use matrix::*;
+use scalar::Scalar;
 use sparse::Sparse;
 
+pub mod amg;
+pub mod banded;
 pub mod base;
+pub mod cholesky;
+pub mod coloring;
 pub mod comparisons;
+pub mod condition;
+pub mod eigen;
+#[cfg(test)]
+mod fixtures;
+pub mod kaczmarz;
+pub mod krylov;
+pub mod lu;
 pub mod matrix;
 pub mod monte_carlo;
+pub mod operator;
+pub mod parallel;
+pub mod preconditioner;
+pub mod qr;
+pub mod rational;
+pub mod refinement;
+pub mod scalar;
+pub mod schwarz;
 pub mod sparse;
+pub mod spectral;
+pub mod stopping;
 
 #[derive(Debug)]
 pub struct Sets(Vec<Vec<Vec<usize>>>);
@@ -71,47 +94,37 @@ impl Config {
             inters.push(Intersection::new(i, false, false, false));
         }
 
-        for i in 1..sets.0[1][0].len() {
-            for inter in &mut inters {
-                if inter.id == sets.0[1][0][i] {
-                    inter.well = true;
-                }
+        // Intersection ids run from 1 to inters_count, so id - 1 is the
+        // index. Ids outside of that range are ignored.
+        for &id in &sets.0[1][0][1..] {
+            if let Some(inter) = inters.get_mut(id.wrapping_sub(1)) {
+                inter.well = true;
             }
         }
 
-        for i in 1..sets.0[1][1].len() {
-            for inter in &mut inters {
-                if inter.id == sets.0[1][1][i] {
-                    inter.exit = true;
-                }
+        for &id in &sets.0[1][1][1..] {
+            if let Some(inter) = inters.get_mut(id.wrapping_sub(1)) {
+                inter.exit = true;
             }
         }
 
         let mut starting_pos = 0;
-        let start = sets.0[1][2][1];
-        for inter in &mut inters {
-            if inter.id == start {
-                inter.start = true;
-                starting_pos = inter.id - 1;
-            }
+        if let Some(inter) = inters.get_mut(sets.0[1][2][1].wrapping_sub(1)) {
+            inter.start = true;
+            starting_pos = inter.id - 1;
         }
 
+        let find = |id: usize| {
+            inters
+                .get(id.wrapping_sub(1))
+                .cloned()
+                .unwrap_or_else(|| Intersection::new(0, false, false, false))
+        };
         let mut alleys = Vec::new();
         for i in 1..alleys_count + 1 {
-            let a_id = sets.0[0][i][0];
-            let b_id = sets.0[0][i][1];
-            let mut a = Intersection::new(0, false, false, false);
-            let mut b = Intersection::new(0, false, false, false);
-            let length = sets.0[0][i][2];
-            for inter in &inters {
-                if inter.id == a_id {
-                    a = inter.clone();
-                }
-                if inter.id == b_id {
-                    b = inter.clone();
-                }
-            }
-            alleys.push(Alley::new(a, b, length));
+            let a = find(sets.0[0][i][0]);
+            let b = find(sets.0[0][i][1]);
+            alleys.push(Alley::new(a, b, sets.0[0][i][2]));
         }
 
         Self {
@@ -120,6 +133,144 @@ impl Config {
             starting_pos,
         }
     }
+
+    /// Indices of the intersections that are neither an exit nor a well,
+    /// in the order used by the reduced systems.
+    pub fn interior(&self) -> Vec<usize> {
+        (0..self.inters.len())
+            .filter(|&i| !self.inters[i].exit && !self.inters[i].well)
+            .collect()
+    }
+
+    /// Neighbouring intersections of every intersection, sorted, each one
+    /// listed once however many alleys join them. Loops are left out.
+    pub fn adjacency(&self) -> Vec<Vec<usize>> {
+        let mut neighbours = vec![Vec::new(); self.inters.len()];
+        for alley in &self.alleys {
+            let (a, b) = (alley.a.id - 1, alley.b.id - 1);
+            if a != b {
+                neighbours[a].push(b);
+                neighbours[b].push(a);
+            }
+        }
+        for list in &mut neighbours {
+            list.sort_unstable();
+            list.dedup();
+        }
+        neighbours
+    }
+
+    /// Every alley seen from both of its ends, as (i, j, length) for the
+    /// way from intersection i to intersection j. Alleys joining the same
+    /// pair each show up on their own and a loop from i back to i twice,
+    /// so every system built from these adds them up the same way.
+    pub(crate) fn alley_ends(&self) -> impl Iterator<Item = (usize, usize, usize)> + '_ {
+        self.alleys.iter().flat_map(|alley| {
+            let (a, b) = (alley.a.id - 1, alley.b.id - 1);
+            [(a, b, alley.length), (b, a, alley.length)]
+        })
+    }
+
+    /// alley_ends with the conductance 1 / length of every alley, the
+    /// weight the walker picks it with.
+    pub(crate) fn conductances<T: Scalar>(&self) -> impl Iterator<Item = (usize, usize, T)> + '_ {
+        self.alley_ends()
+            .map(|(i, j, length)| (i, j, T::ONE / T::from_f64(length as f64)))
+    }
+
+    /// Sum of the conductances at every intersection.
+    pub(crate) fn conductance_degrees<T: Scalar>(&self) -> Vec<T> {
+        let mut degrees = vec![T::ZERO; self.inters.len()];
+        for (i, _, weight) in self.conductances::<T>() {
+            degrees[i] += weight;
+        }
+        degrees
+    }
+
+    /// Fundamental matrix N = (I - Q)⁻¹ of the absorbing chain, where Q
+    /// holds the transition probabilities between the interior
+    /// intersections. N[k][l] is the expected number of visits to
+    /// interior()[l] of a walk started from interior()[k].
+    pub fn fundamental_matrix(&self) -> Result<Matrix, MatrixError> {
+        let (mat, _) = Matrix::from_config(self);
+        let interior = self.interior();
+        let transient = Matrix::from_vecs(
+            interior
+                .iter()
+                .map(|&i| interior.iter().map(|&j| mat.rows[i][j]).collect())
+                .collect(),
+        );
+        transient.inverse()
+    }
+
+    /// Intersections in the order they appear along the park, if the
+    /// alleys join them into a single chain without branches or loops.
+    pub fn chain_order(&self) -> Option<Vec<usize>> {
+        let n = self.inters.len();
+        if n == 0 || self.alleys.len() != n - 1 {
+            return None;
+        }
+
+        let mut neighbours = vec![Vec::new(); n];
+        for alley in &self.alleys {
+            let (a, b) = (alley.a.id - 1, alley.b.id - 1);
+            if a == b {
+                return None;
+            }
+            neighbours[a].push(b);
+            neighbours[b].push(a);
+        }
+        if neighbours.iter().any(|nb| nb.len() > 2) {
+            return None;
+        }
+
+        // n - 1 alleys and no branches, so walking from one end has to
+        // visit every intersection, unless the park falls apart.
+        let mut current = (0..n).find(|&i| neighbours[i].len() < 2)?;
+        let mut previous = None;
+        let mut order = vec![current];
+        while let Some(&next) = neighbours[current].iter().find(|&&j| Some(j) != previous) {
+            previous = Some(current);
+            current = next;
+            order.push(current);
+        }
+
+        (order.len() == n).then_some(order)
+    }
+
+    /// Solves the park with the Thomas algorithm if it is a chain, and
+    /// with sparse LU otherwise.
+    pub fn solve(&self) -> Result<Vec<f64>, MatrixError> {
+        let order = match self.chain_order() {
+            Some(order) => order,
+            None => {
+                let (sparse, b) = Sparse::from_config(self);
+                return sparse.gaussian_partial_pivot(&b);
+            }
+        };
+
+        let (banded, b) = Banded::from_config_chain(self, &order);
+        let y = banded.solve_tridiagonal(&b)?;
+        let mut x = vec![0f64; y.len()];
+        for (&i, yi) in order.iter().zip(y) {
+            x[i] = yi;
+        }
+        Ok(x)
+    }
+
+    /// Turns a solution of a reduced system back into one value per
+    /// intersection, with 1 for the exits and 0 for the wells.
+    pub fn expand_interior(&self, x: &[f64]) -> Vec<f64> {
+        let mut out: Vec<f64> = self
+            .inters
+            .iter()
+            .map(|inter| if inter.exit { 1f64 } else { 0f64 })
+            .collect();
+        for (&i, &val) in self.interior().iter().zip(x) {
+            out[i] = val;
+        }
+        out
+    }
 }
 
 pub fn parse_config(file_name: &'static str) -> Sets {
@@ -252,10 +403,28 @@ fn main() {
 
             comparisons::time_all(&config);
         }
+        "compare-precond" => {
+            let sets = parse_config("tmp.config");
+            let config = Config::build(sets);
+
+            if let Err(e) = comparisons::compare_preconditioners(&config) {
+                eprintln!("{}", e);
+                process::exit(0);
+            }
+        }
+        "compare-precision" => {
+            let sets = parse_config("tmp.config");
+            let config = Config::build(sets);
+
+            if let Err(e) = comparisons::compare_precisions(&config) {
+                eprintln!("{}", e);
+                process::exit(0);
+            }
+        }
         "from-cfg" => {
             let sets = parse_config("tmp.config");
             let config = Config::build(sets);
-            let (mat, _) = Matrix::from_config(&config);
+            let (mat, _) = Matrix::<f64>::from_config(&config);
             let (sparse, b) = Sparse::from_config(&config);
 
             let mc_res = monte_carlo::simulate_park_walk(&config, config.inters.len() * 10);
@@ -267,6 +436,33 @@ fn main() {
             let sp_res = sparse.gaussian(&b).unwrap();
             println!("sp gauss: {:?}", sp_res[config.starting_pos]);
         }
+        "fundamental" => {
+            let sets = parse_config("tmp.config");
+            let config = Config::build(sets);
+
+            let fundamental = match config.fundamental_matrix() {
+                Ok(n) => n,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(0);
+                }
+            };
+            if let Err(e) = fundamental.to_file("dump/fundamental_matrix.csv") {
+                eprintln!("{}", e);
+                process::exit(0);
+            }
+
+            // Row sums of N are the expected numbers of steps until the
+            // walk hits an exit or a well.
+            if let Some(k) = config
+                .interior()
+                .iter()
+                .position(|&i| i == config.starting_pos)
+            {
+                let steps: f64 = fundamental.rows[k].iter().sum();
+                println!("expected steps from the start: {}", steps);
+            }
+        }
         "verify-mc" => {
             if let Err(e) = incremental_verify_mc(100, None) {
                 eprintln!("{}", e);
@@ -2,14 +2,21 @@ use std::error::Error;
 use std::{fs::File, io::Write};
 
 use crate::base::*;
+use crate::krylov;
+use crate::lu::LuFactorization;
+use crate::operator;
+use crate::preconditioner::{Identity, Preconditioner};
+use crate::scalar::Scalar;
+use crate::spectral::{self, Dominance};
+use crate::stopping::{Monitor, StoppingCriterion, StoppingTest};
 use crate::Config;
 
 #[derive(Clone, Debug, PartialEq)]
-pub struct Matrix {
-    pub rows: Vec<Vec<f64>>,
+pub struct Matrix<T: Scalar = f64> {
+    pub rows: Vec<Vec<T>>,
 }
 
-impl std::fmt::Display for Matrix {
+impl<T: Scalar> std::fmt::Display for Matrix<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         for row in &self.rows {
             writeln!(f, "{:>8.4?}", row)?
@@ -18,50 +25,97 @@ impl std::fmt::Display for Matrix {
     }
 }
 
-impl Matrix {
+impl<T: Scalar> Matrix<T> {
     pub fn new() -> Self {
         Self { rows: vec![] }
     }
 
     pub fn from_size(rows: usize, cols: usize) -> Self {
         Self {
-            rows: vec![vec![0f64; cols]; rows],
+            rows: vec![vec![T::ZERO; cols]; rows],
         }
     }
 
-    pub fn from_vecs(vec_matrix: Vec<Vec<f64>>) -> Self {
+    pub fn from_vecs(vec_matrix: Vec<Vec<T>>) -> Self {
         Self { rows: vec_matrix }
     }
 
-    pub fn from_config(cfg: &Config) -> (Self, Vec<f64>) {
+    pub fn from_config(cfg: &Config) -> (Self, Vec<T>) {
         let n = cfg.inters.len();
+        let degrees = cfg.conductance_degrees::<T>();
         let mut out = Self::from_size(n, n);
-        let mut b = vec![0f64; n];
+        let mut b = vec![T::ZERO; n];
 
         for i in 0..n {
-            out.rows[i][i] = 1f64;
+            out.rows[i][i] = T::ONE;
             if cfg.inters[i].exit {
-                b[i] = 1f64;
+                b[i] = T::ONE;
             }
         }
 
-        for i in 0..n {
+        for (i, j, weight) in cfg.conductances::<T>() {
+            if !cfg.inters[i].exit && !cfg.inters[i].well {
+                out.rows[i][j] -= weight / degrees[i];
+            }
+        }
+
+        (out, b)
+    }
+
+    /// Builds the same system as from_config, but with every interior row
+    /// scaled back by its denom and the exits and wells moved to the
+    /// right-hand side. The result is symmetric positive definite as long
+    /// as every intersection can reach an exit or a well.
+    pub fn from_config_symmetric(cfg: &Config) -> (Self, Vec<T>) {
+        let n = cfg.inters.len();
+        let mut out = Self::from_size(n, n);
+        let mut b = vec![T::ZERO; n];
+
+        for (i, inter) in cfg.inters.iter().enumerate() {
+            if inter.exit || inter.well {
+                out.rows[i][i] = T::ONE;
+                if inter.exit {
+                    b[i] = T::ONE;
+                }
+            }
+        }
+
+        for (i, j, weight) in cfg.conductances::<T>() {
             if cfg.inters[i].exit || cfg.inters[i].well {
                 continue;
             }
 
-            let mut denom = 0f64;
-            for alley in &cfg.alleys {
-                if cfg.inters[i].id == alley.a.id || cfg.inters[i].id == alley.b.id {
-                    denom += 1f64 / alley.length as f64;
-                }
+            out.rows[i][i] += weight;
+            if cfg.inters[j].exit {
+                b[i] += weight;
+            } else if !cfg.inters[j].well {
+                out.rows[i][j] -= weight;
             }
+        }
 
-            for alley in &cfg.alleys {
-                if cfg.inters[i].id == alley.a.id {
-                    out.rows[i][alley.b.id - 1] = -(1f64 / alley.length as f64) / denom;
-                } else if cfg.inters[i].id == alley.b.id {
-                    out.rows[i][alley.a.id - 1] = -(1f64 / alley.length as f64) / denom;
+        (out, b)
+    }
+
+    /// The symmetric positive definite Laplacian restricted to the interior
+    /// intersections (see Config::interior), with the exits and wells
+    /// moved to the right-hand side.
+    pub fn reduced_laplacian(cfg: &Config) -> (Self, Vec<T>) {
+        let interior = cfg.interior();
+        let mut position = vec![None; cfg.inters.len()];
+        for (k, &i) in interior.iter().enumerate() {
+            position[i] = Some(k);
+        }
+
+        let mut out = Self::from_size(interior.len(), interior.len());
+        let mut b = vec![T::ZERO; interior.len()];
+
+        for (i, j, weight) in cfg.conductances::<T>() {
+            if let Some(k) = position[i] {
+                out.rows[k][k] += weight;
+                if cfg.inters[j].exit {
+                    b[k] += weight;
+                } else if let Some(l) = position[j] {
+                    out.rows[k][l] -= weight;
                 }
             }
         }
@@ -81,7 +135,7 @@ impl Matrix {
         Ok(())
     }
 
-    pub fn vec_to_file(v: &Vec<f64>, file_path: &'static str) -> Result<(), Box<dyn Error>> {
+    pub fn vec_to_file(v: &Vec<T>, file_path: &'static str) -> Result<(), Box<dyn Error>> {
         let mut file = File::create(file_path)?;
         let out: Vec<String> = v.iter().map(|x| x.to_string()).collect();
         let out = out.join("\n");
@@ -89,7 +143,7 @@ impl Matrix {
         Ok(())
     }
 
-    pub fn multiply(&self, other: &Self) -> Result<Self, MatrixError> {
+    pub fn multiply(&self, other: &Self) -> Result<Self, MatrixError<T>> {
         if self.rows[0].len() != other.rows.len() {
             return Err(MatrixError::SizeError);
         }
@@ -107,12 +161,12 @@ impl Matrix {
         Ok(out)
     }
 
-    pub fn multiply_by_vec(&self, other: &Vec<f64>) -> Result<Vec<f64>, MatrixError> {
+    pub fn multiply_by_vec(&self, other: &[T]) -> Result<Vec<T>, MatrixError<T>> {
         if self.rows[0].len() != other.len() {
             return Err(MatrixError::SizeError);
         }
 
-        let mut out = vec![0f64; other.len()];
+        let mut out = vec![T::ZERO; other.len()];
 
         for i in 0..self.rows.len() {
             for j in 0..other.len() {
@@ -124,142 +178,423 @@ impl Matrix {
     }
 
     // https://en.wikipedia.org/wiki/Dot_product
-    pub fn dot_product(x: &[f64], y: &[f64]) -> f64 {
+    pub fn dot_product(x: &[T], y: &[T]) -> T {
         return x.iter().zip(y.iter()).map(|(&a, &b)| a * b).sum();
     }
+
+    // Zeroes column i below the diagonal, using row i as the pivot row.
+    fn eliminate_below(&mut self, b: &mut [T], i: usize) {
+        let (top, bottom) = self.rows.split_at_mut(i + 1);
+        let pivot_row = &top[i];
+        for (offset, row) in bottom.iter_mut().enumerate() {
+            let factor = row[i] / pivot_row[i];
+            for (val, &pivot_val) in row[i..].iter_mut().zip(&pivot_row[i..]) {
+                *val -= factor * pivot_val;
+            }
+            let update = factor * b[i];
+            b[i + 1 + offset] -= update;
+        }
+    }
+
+    // Solves an upper triangular system.
+    fn back_substitution(&self, b: &[T]) -> Vec<T> {
+        let mut out = vec![T::ZERO; b.len()];
+        for i in (0..b.len()).rev() {
+            out[i] =
+                (b[i] - Self::dot_product(&self.rows[i][i + 1..], &out[i + 1..])) / self.rows[i][i];
+        }
+        out
+    }
+
+    // One in-place SOR sweep, backwards if reverse is set.
+    fn sor_sweep(&self, b: &[T], x: &mut [T], omega: T, reverse: bool) {
+        let order: Box<dyn Iterator<Item = usize>> = if reverse {
+            Box::new((0..b.len()).rev())
+        } else {
+            Box::new(0..b.len())
+        };
+
+        for i in order {
+            let sigma = Self::dot_product(&self.rows[i][..i], &x[..i])
+                + Self::dot_product(&self.rows[i][i + 1..], &x[i + 1..]);
+            x[i] = (T::ONE - omega) * x[i] + omega * (b[i] - sigma) / self.rows[i][i];
+        }
+    }
 }
 
-impl MatrixBase for Matrix {
+impl<T: Scalar> MatrixBase<T> for Matrix<T> {
     fn init_default_path(size: usize) -> Self {
         let mut out = Self::from_size(size, size);
 
-        out.rows[0][0] = 1f64;
-        out.rows[size - 1][size - 1] = 1f64;
+        out.rows[0][0] = T::ONE;
+        out.rows[size - 1][size - 1] = T::ONE;
 
         for i in 1..size - 1 {
-            out.rows[i][i - 1] = -0.5;
-            out.rows[i][i] = 1f64;
-            out.rows[i][i + 1] = -0.5;
+            out.rows[i][i - 1] = -T::from_f64(0.5);
+            out.rows[i][i] = T::ONE;
+            out.rows[i][i + 1] = -T::from_f64(0.5);
         }
 
         out
     }
 
-    fn jacobi(&self, b: &Vec<f64>, x0: &Vec<f64>, eps: f64, max_iter: usize) -> Vec<f64> {
-        let mut x = x0.clone();
+    fn jacobi(
+        &self,
+        b: &Vec<T>,
+        x0: &Vec<T>,
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        let stop = StoppingCriterion::new(StoppingTest::AbsoluteStep(eps), max_iter);
+        self.jacobi_until(b, x0, &stop)
+    }
 
-        for it in 0..max_iter {
-            let mut x_new = vec![0.0; b.len()];
-            let mut error = 0f64;
-            for i in 0..b.len() {
-                x_new[i] = (b[i]
-                    - Self::dot_product(&self.rows[i][..i], &x[..i])
-                    - Self::dot_product(&self.rows[i][i + 1..], &x[i + 1..]))
-                    / self.rows[i][i];
+    // The same iteration as the matrix-free one, see operator::jacobi.
+    fn jacobi_until(
+        &self,
+        b: &[T],
+        x0: &[T],
+        stop: &StoppingCriterion,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        if b.len() != self.rows.len() || x0.len() != self.rows.len() {
+            return Err(MatrixError::SizeError);
+        }
+        operator::jacobi(self, b, x0, stop)
+    }
 
-                error = error.max((x_new[i] - x[i]).abs());
-            }
+    fn gaussian(&self, b: &Vec<T>) -> Result<Vec<T>, MatrixError<T>> {
+        let mut a = self.clone();
+        let mut b_new = b.clone();
 
-            if error < eps {
-                println!("jacobi breaking at: {} iterations", it);
-                break;
+        for i in 0..b_new.len() {
+            if a.rows[i][i] == T::ZERO {
+                return Err(MatrixError::ZeroPivotError);
             }
 
-            x = x_new;
+            a.eliminate_below(&mut b_new, i);
         }
 
-        x
+        Ok(a.back_substitution(&b_new))
+    }
+
+    // The pivot row is chosen again at every elimination step, after the
+    // previous steps have already changed the column, see LuFactorization.
+    fn gaussian_partial_pivot(&self, b: &Vec<T>) -> Result<Vec<T>, MatrixError<T>> {
+        LuFactorization::from_matrix(self)?.solve(b)
     }
 
-    fn gaussian(&self, b: &Vec<f64>) -> Result<Vec<f64>, MatrixError> {
+    // https://en.wikipedia.org/wiki/Pivot_element#Complete_pivoting
+    fn gaussian_complete_pivot(&self, b: &[T]) -> Result<Vec<T>, MatrixError<T>> {
+        let n = b.len();
         let mut a = self.clone();
-        let mut b_new = b.clone();
+        let mut b_new = b.to_vec();
+        // unknowns[k] is the unknown that ended up in column k.
+        let mut unknowns: Vec<usize> = (0..n).collect();
 
-        for i in 0..b_new.len() {
-            if a.rows[i][i] == 0.0 {
-                return Err(MatrixError::ZeroPivotError);
+        for i in 0..n {
+            let (mut max_row, mut max_col) = (i, i);
+            for (r, row) in a.rows.iter().enumerate().skip(i) {
+                for (c, val) in row.iter().enumerate().skip(i) {
+                    if val.abs() > a.rows[max_row][max_col].abs() {
+                        (max_row, max_col) = (r, c);
+                    }
+                }
             }
 
-            for j in (i + 1)..b_new.len() {
-                let factor = a.rows[j][i] / a.rows[i][i];
-                for k in i..b_new.len() {
-                    a.rows[j][k] -= factor * a.rows[i][k];
+            if a.rows[max_row][max_col] == T::ZERO {
+                return Err(MatrixError::Unsolvable);
+            }
+
+            a.rows.swap(i, max_row);
+            b_new.swap(i, max_row);
+            if max_col != i {
+                for row in &mut a.rows {
+                    row.swap(i, max_col);
                 }
-                b_new[j] -= factor * b_new[i];
+                unknowns.swap(i, max_col);
             }
+
+            a.eliminate_below(&mut b_new, i);
         }
 
-        let mut out = vec![0f64; b_new.len()];
-        for i in (0..b_new.len()).rev() {
-            out[i] = b_new[i];
-            for j in (i + 1)..b_new.len() {
-                out[i] -= a.rows[i][j] * out[j];
-            }
-            out[i] /= a.rows[i][i];
+        let mut out = vec![T::ZERO; n];
+        for (k, value) in a.back_substitution(&b_new).into_iter().enumerate() {
+            out[unknowns[k]] = value;
         }
 
         Ok(out)
     }
 
-    fn partial_pivot(&self, b: &Vec<f64>) -> (Self, Vec<f64>) {
+    // https://en.wikipedia.org/wiki/Gaussian_elimination#Pivoting
+    //
+    // Like partial pivoting, but every row competes with its entry
+    // divided by the largest entry of the original row.
+    fn gaussian_scaled_pivot(&self, b: &[T]) -> Result<Vec<T>, MatrixError<T>> {
+        let n = b.len();
         let mut a = self.clone();
-        let mut b_new = b.clone();
+        let mut b_new = b.to_vec();
+        let mut scales: Vec<T> = a
+            .rows
+            .iter()
+            .map(|row| row.iter().fold(T::ZERO, |max, val| max.max(val.abs())))
+            .collect();
 
-        for i in 0..b_new.len() {
+        for i in 0..n {
             let mut max_row = i;
-            for k in (i + 1)..b_new.len() {
-                if a.rows[k][i].abs() > a.rows[max_row][i].abs() {
-                    max_row = k;
+            let mut max_ratio = T::ZERO;
+            for (k, (row, scale)) in a.rows.iter().zip(&scales).enumerate().skip(i) {
+                if *scale == T::ZERO {
+                    continue;
+                }
+                let ratio = row[i].abs() / *scale;
+                if ratio > max_ratio {
+                    (max_row, max_ratio) = (k, ratio);
                 }
             }
 
-            if max_row != i {
-                a.rows.swap(i, max_row);
-                b_new.swap(i, max_row);
+            if max_ratio == T::ZERO {
+                return Err(MatrixError::Unsolvable);
             }
-        }
 
-        (a, b_new)
-    }
+            a.rows.swap(i, max_row);
+            b_new.swap(i, max_row);
+            scales.swap(i, max_row);
 
-    fn gaussian_partial_pivot(&self, b: &Vec<f64>) -> Result<Vec<f64>, MatrixError> {
-        let (a, b_new) = self.partial_pivot(b);
-        match a.gaussian(&b_new) {
-            Ok(values) => Ok(values),
-            Err(_) => Err(MatrixError::Unsolvable),
+            a.eliminate_below(&mut b_new, i);
         }
+
+        Ok(a.back_substitution(&b_new))
     }
 
-    fn gauss_seidel(&self, b: &Vec<f64>, x0: &Vec<f64>, eps: f64, max_iter: usize) -> Vec<f64> {
-        let mut x = x0.clone();
+    fn gauss_seidel(
+        &self,
+        b: &Vec<T>,
+        x0: &Vec<T>,
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        let stop = StoppingCriterion::new(StoppingTest::AbsoluteStep(eps), max_iter);
+        self.gauss_seidel_until(b, x0, &stop)
+    }
 
-        for it in 0..max_iter {
-            let mut x_new = vec![0f64; b.len()];
-            let mut error = 0f64;
+    fn gauss_seidel_until(
+        &self,
+        b: &[T],
+        x0: &[T],
+        stop: &StoppingCriterion,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        let mul = |v: &[T]| self.multiply_by_vec(v).unwrap();
+        let mut monitor = Monitor::new(stop, b);
+        let mut x = x0.to_vec();
+
+        while monitor.running() {
+            let mut x_new = vec![T::ZERO; b.len()];
             for i in 0..b.len() {
                 x_new[i] = (b[i]
                     - Self::dot_product(&self.rows[i][..i], &x_new[..i])
                     - Self::dot_product(&self.rows[i][i + 1..], &x[i + 1..]))
                     / self.rows[i][i];
-
-                error = error.max((x_new[i] - x[i]).abs());
-            }
-
-            if error < eps {
-                println!("seidel breaking at: {} iterations", it);
-                break;
             }
 
+            monitor.update(&x, &x_new, mul);
             x = x_new;
         }
 
-        x
+        let ax = self.multiply_by_vec(&x)?;
+        monitor.finish(x, &ax)
+    }
+
+    fn conjugate_gradient(
+        &self,
+        b: &[T],
+        x0: &[T],
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        self.preconditioned_conjugate_gradient(b, x0, &Identity, eps, max_iter)
+    }
+
+    fn gmres(
+        &self,
+        b: &[T],
+        x0: &[T],
+        restart: usize,
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        self.preconditioned_gmres(b, x0, &Identity, restart, eps, max_iter)
+    }
+
+    fn bicgstab(
+        &self,
+        b: &[T],
+        x0: &[T],
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        self.preconditioned_bicgstab(b, x0, &Identity, eps, max_iter)
+    }
+
+    fn preconditioned_conjugate_gradient(
+        &self,
+        b: &[T],
+        x0: &[T],
+        precond: &dyn Preconditioner<T>,
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        krylov::conjugate_gradient(self, b, x0, precond, eps, max_iter)
+    }
+
+    fn preconditioned_gmres(
+        &self,
+        b: &[T],
+        x0: &[T],
+        precond: &dyn Preconditioner<T>,
+        restart: usize,
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        krylov::gmres(self, b, x0, precond, restart, eps, max_iter)
+    }
+
+    fn preconditioned_bicgstab(
+        &self,
+        b: &[T],
+        x0: &[T],
+        precond: &dyn Preconditioner<T>,
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        krylov::bicgstab(self, b, x0, precond, eps, max_iter)
+    }
+
+    fn richardson(
+        &self,
+        b: &[T],
+        x0: &[T],
+        precond: &dyn Preconditioner<T>,
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        krylov::richardson(self, b, x0, precond, eps, max_iter)
+    }
+
+    fn jacobi_spectral_radius(&self, max_iter: usize) -> f64 {
+        let apply = |v: &[T]| {
+            (0..self.rows.len())
+                .map(|i| {
+                    -(Self::dot_product(&self.rows[i][..i], &v[..i])
+                        + Self::dot_product(&self.rows[i][i + 1..], &v[i + 1..]))
+                        / self.rows[i][i]
+                })
+                .collect()
+        };
+        spectral::power_radius(apply, self.rows.len(), max_iter)
+    }
+
+    fn gauss_seidel_spectral_radius(&self, max_iter: usize) -> f64 {
+        // G = -(D + L)⁻¹U, which is one Gauss-Seidel sweep for Ax = 0.
+        let zeros = vec![T::ZERO; self.rows.len()];
+        let apply = |v: &[T]| {
+            let mut x = v.to_vec();
+            self.sor_sweep(&zeros, &mut x, T::ONE, false);
+            x
+        };
+        spectral::power_radius(apply, self.rows.len(), max_iter)
+    }
+
+    fn diagonal_dominance(&self) -> Dominance {
+        spectral::dominance(
+            self.rows.iter().enumerate().map(|(i, row)| {
+                let off_diag = row
+                    .iter()
+                    .enumerate()
+                    .filter(|&(j, _)| j != i)
+                    .map(|(_, val)| val.abs().to_f64())
+                    .sum();
+                (row[i].to_f64(), off_diag)
+            }),
+            T::EPSILON,
+        )
+    }
+
+    // https://en.wikipedia.org/wiki/Successive_over-relaxation
+    fn sor(
+        &self,
+        b: &[T],
+        x0: &[T],
+        omega: Relaxation,
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        let stop = StoppingCriterion::new(StoppingTest::AbsoluteStep(eps), max_iter);
+        self.sor_until(b, x0, omega, &stop)
+    }
+
+    fn sor_until(
+        &self,
+        b: &[T],
+        x0: &[T],
+        omega: Relaxation,
+        stop: &StoppingCriterion,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        let omega = T::from_f64(omega.factor(self));
+        let mul = |v: &[T]| self.multiply_by_vec(v).unwrap();
+        let mut monitor = Monitor::new(stop, b);
+        let mut x = x0.to_vec();
+
+        while monitor.running() {
+            let prev = x.clone();
+            self.sor_sweep(b, &mut x, omega, false);
+            monitor.update(&prev, &x, mul);
+        }
+
+        let ax = self.multiply_by_vec(&x)?;
+        monitor.finish(x, &ax)
+    }
+
+    // https://en.wikipedia.org/wiki/Symmetric_successive_over-relaxation
+    fn ssor(
+        &self,
+        b: &[T],
+        x0: &[T],
+        omega: Relaxation,
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        let stop = StoppingCriterion::new(StoppingTest::AbsoluteStep(eps), max_iter);
+        self.ssor_until(b, x0, omega, &stop)
+    }
+
+    fn ssor_until(
+        &self,
+        b: &[T],
+        x0: &[T],
+        omega: Relaxation,
+        stop: &StoppingCriterion,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        let omega = T::from_f64(omega.factor(self));
+        let mul = |v: &[T]| self.multiply_by_vec(v).unwrap();
+        let mut monitor = Monitor::new(stop, b);
+        let mut x = x0.to_vec();
+
+        while monitor.running() {
+            let prev = x.clone();
+            self.sor_sweep(b, &mut x, omega, false);
+            self.sor_sweep(b, &mut x, omega, true);
+            monitor.update(&prev, &x, mul);
+        }
+
+        let ax = self.multiply_by_vec(&x)?;
+        monitor.finish(x, &ax)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sparse::Sparse;
 
     #[test]
     fn test_multiply() {
@@ -284,4 +619,149 @@ mod tests {
         let expected = vec![1.0, 2.0, 1.0];
         assert_eq!(expected, a.multiply_by_vec(&b).unwrap());
     }
+
+    #[test]
+    fn test_conjugate_gradient() {
+        let cfg = Config::build(crate::parse_config("default.config"));
+        let (a, b) = Matrix::from_config(&cfg);
+        let (spd, spd_b) = Matrix::from_config_symmetric(&cfg);
+        assert_eq!(spd, Matrix::from_vecs(transpose(&spd.rows)));
+
+        let expected = a.gaussian_partial_pivot(&b).unwrap();
+        let x0 = vec![0f64; b.len()];
+        let result = spd
+            .conjugate_gradient(&spd_b, &x0, 1e-12, 100)
+            .unwrap()
+            .solution;
+        assert!(crate::comparisons::compare_vecs(&expected, &result, 1e-10));
+    }
+
+    #[test]
+    fn test_gmres_bicgstab() {
+        let a = Matrix::from_vecs(vec![
+            vec![4.0, 1.0, 0.0],
+            vec![2.0, 5.0, 1.0],
+            vec![0.0, 3.0, 6.0],
+        ]);
+        let b = vec![1.0, 2.0, 3.0];
+        let x0 = vec![0f64; 3];
+        let expected = a.gaussian_partial_pivot(&b).unwrap();
+
+        let gmres = a.gmres(&b, &x0, 2, 1e-12, 100).unwrap();
+        let history = gmres.residual_history.unwrap();
+        assert_eq!(history.len(), gmres.iterations + 1);
+        assert!(crate::comparisons::compare_vecs(
+            &expected,
+            &gmres.solution,
+            1e-10
+        ));
+        let bicgstab = a.bicgstab(&b, &x0, 1e-12, 100).unwrap().solution;
+        assert!(crate::comparisons::compare_vecs(
+            &expected, &bicgstab, 1e-10
+        ));
+    }
+
+    #[test]
+    fn test_sor_ssor() {
+        let n = 20;
+        let a = Matrix::init_default_path(n);
+        let mut b = vec![0f64; n];
+        b[0] = 1f64;
+        let x0 = vec![0f64; n];
+        let expected: Vec<f64> = (0..n).map(|i| 1.0 - i as f64 / (n - 1) as f64).collect();
+
+        let rho = (std::f64::consts::PI / (n - 1) as f64).cos();
+        assert!((a.jacobi_spectral_radius(10_000) - rho).abs() < 1e-6);
+
+        let sor = a
+            .sor(&b, &x0, Relaxation::Auto, 1e-14, 10_000)
+            .unwrap()
+            .solution;
+        assert!(crate::comparisons::compare_vecs(&expected, &sor, 1e-10));
+        let ssor = a
+            .ssor(&b, &x0, Relaxation::Fixed(1.5), 1e-14, 10_000)
+            .unwrap()
+            .solution;
+        assert!(crate::comparisons::compare_vecs(&expected, &ssor, 1e-10));
+    }
+
+    #[test]
+    fn test_gaussian_partial_pivot() {
+        // Elimination without pivoting hits a zero pivot in the second
+        // column, the largest entry of every column is already on the
+        // diagonal, so only pivoting during the elimination gets past it.
+        let a = Matrix::from_vecs(vec![
+            vec![1.0, 1.0, 0.0],
+            vec![1.0, 1.0, 1.0],
+            vec![0.0, 1.0, 1.0],
+        ]);
+        let b = vec![2.0, 3.0, 2.0];
+        assert!(a.gaussian(&b).is_err());
+        assert_eq!(a.gaussian_partial_pivot(&b).unwrap(), vec![1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_complete_and_scaled_pivot() {
+        let a = Matrix::from_vecs(vec![
+            vec![30.0, 591400.0, 0.0],
+            vec![5.291, -6.13, 1.0],
+            vec![0.0, 2.0, 1e-3],
+        ]);
+        let expected = vec![10.0, 1.0, 2.0];
+        let b = a.multiply_by_vec(&expected).unwrap();
+
+        let complete = a.gaussian_complete_pivot(&b).unwrap();
+        assert!(crate::comparisons::compare_vecs(&expected, &complete, 1e-9));
+        let scaled = a.gaussian_scaled_pivot(&b).unwrap();
+        assert!(crate::comparisons::compare_vecs(&expected, &scaled, 1e-9));
+    }
+
+    #[test]
+    fn test_parallel_alleys() {
+        // Two alleys of length 4 between 2 and 3 conduct like a single
+        // one of length 2.
+        let park = |alleys: Vec<Vec<usize>>| {
+            let mut first = vec![vec![4, alleys.len()]];
+            first.extend(alleys);
+            Config::build(crate::Sets(vec![
+                first,
+                vec![vec![1, 1], vec![2, 2, 4], vec![1, 3]],
+            ]))
+        };
+        let doubled = park(vec![
+            vec![1, 2, 4],
+            vec![2, 3, 4],
+            vec![3, 2, 4],
+            vec![3, 4, 4],
+        ]);
+        let single = park(vec![vec![1, 2, 4], vec![2, 3, 2], vec![3, 4, 4]]);
+
+        let systems = [
+            Matrix::<f64>::from_config,
+            Matrix::from_config_symmetric,
+            Matrix::reduced_laplacian,
+        ];
+        let sparse_systems = [
+            Sparse::<f64>::from_config,
+            Sparse::from_config_symmetric,
+            Sparse::reduced_laplacian,
+        ];
+        for (system, sparse_system) in systems.iter().zip(sparse_systems) {
+            let (a, b) = system(&doubled);
+            assert_eq!((a.clone(), b.clone()), system(&single));
+            let (sparse, sparse_b) = sparse_system(&doubled);
+            assert_eq!(sparse_b, b);
+            for (i, row) in a.rows.iter().enumerate() {
+                for (j, &val) in row.iter().enumerate() {
+                    assert_eq!(sparse.get_value(i, j), val);
+                }
+            }
+        }
+    }
+
+    fn transpose(rows: &[Vec<f64>]) -> Vec<Vec<f64>> {
+        (0..rows[0].len())
+            .map(|j| rows.iter().map(|row| row[j]).collect())
+            .collect()
+    }
 }
@@ -0,0 +1,213 @@
+use crate::base::*;
+use crate::krylov;
+use crate::matrix::Matrix;
+use crate::preconditioner::Jacobi;
+use crate::scalar::Scalar;
+use crate::sparse::Sparse;
+use crate::stopping::{Monitor, StoppingCriterion};
+use crate::Config;
+
+// https://en.wikipedia.org/wiki/Matrix-free_methods
+
+/// Anything that can multiply a vector by A. The iterative solvers never
+/// look at the entries of A, only at products and at its diagonal.
+pub trait LinearOperator<T: Scalar = f64> {
+    fn size(&self) -> usize;
+    fn apply(&self, x: &[T]) -> Vec<T>;
+    fn diagonal(&self) -> Vec<T>;
+}
+
+impl<T: Scalar> LinearOperator<T> for Matrix<T> {
+    fn size(&self) -> usize {
+        self.rows.len()
+    }
+
+    fn apply(&self, x: &[T]) -> Vec<T> {
+        self.multiply_by_vec(x).unwrap()
+    }
+
+    fn diagonal(&self) -> Vec<T> {
+        (0..self.rows.len()).map(|i| self.rows[i][i]).collect()
+    }
+}
+
+impl<T: Scalar> LinearOperator<T> for Sparse<T> {
+    fn size(&self) -> usize {
+        Sparse::size(self)
+    }
+
+    fn apply(&self, x: &[T]) -> Vec<T> {
+        self.multiply_by_vec(x)
+    }
+
+    fn diagonal(&self) -> Vec<T> {
+        (0..Sparse::size(self))
+            .map(|i| self.get_value(i, i))
+            .collect()
+    }
+}
+
+/// The system of Matrix::from_config, computed from the alleys on every
+/// product. The alley list is the only adjacency structure the park
+/// needs, so the memory stays at a few vectors of the park's size. The
+/// conductance degrees are summed once up front rather than per product.
+pub struct ParkOperator<'a, T: Scalar = f64> {
+    cfg: &'a Config,
+    degrees: Vec<T>,
+}
+
+impl<'a, T: Scalar> ParkOperator<'a, T> {
+    pub fn new(cfg: &'a Config) -> Self {
+        Self {
+            cfg,
+            degrees: cfg.conductance_degrees(),
+        }
+    }
+
+    fn absorbing(&self, i: usize) -> bool {
+        self.cfg.inters[i].exit || self.cfg.inters[i].well
+    }
+}
+
+impl<T: Scalar> LinearOperator<T> for ParkOperator<'_, T> {
+    fn size(&self) -> usize {
+        self.cfg.inters.len()
+    }
+
+    fn apply(&self, x: &[T]) -> Vec<T> {
+        let mut out = x.to_vec();
+        for (i, j, weight) in self.cfg.conductances::<T>() {
+            if !self.absorbing(i) {
+                out[i] -= weight / self.degrees[i] * x[j];
+            }
+        }
+        out
+    }
+
+    fn diagonal(&self) -> Vec<T> {
+        // Only an alley leading back to its own intersection touches it.
+        let mut out = vec![T::ONE; self.cfg.inters.len()];
+        for (i, j, weight) in self.cfg.conductances::<T>() {
+            if i == j && !self.absorbing(i) {
+                out[i] -= weight / self.degrees[i];
+            }
+        }
+        out
+    }
+}
+
+impl Config {
+    /// b of the park's system, 1 at the exits and 0 everywhere else.
+    pub fn right_hand_side<T: Scalar>(&self) -> Vec<T> {
+        self.inters
+            .iter()
+            .map(|inter| if inter.exit { T::ONE } else { T::ZERO })
+            .collect()
+    }
+
+    /// Escape probabilities from BiCGSTAB with Jacobi scaling, without
+    /// assembling either a Matrix or a Sparse.
+    pub fn solve_matrix_free(&self, eps: f64, max_iter: usize) -> Result<SolveReport, MatrixError> {
+        let b = self.right_hand_side();
+        let x0 = vec![0f64; b.len()];
+        let a = ParkOperator::new(self);
+        let precond = Jacobi::from_operator(&a)?;
+        krylov::bicgstab(&a, &b, &x0, &precond, eps, max_iter)
+    }
+}
+
+// https://en.wikipedia.org/wiki/Jacobi_method
+//
+// Written as x += D⁻¹(b - Ax), which only needs products with A and its
+// diagonal instead of the off-diagonal entries row by row.
+pub fn jacobi<T: Scalar, A>(
+    a: &A,
+    b: &[T],
+    x0: &[T],
+    stop: &StoppingCriterion,
+) -> Result<SolveReport<T>, MatrixError<T>>
+where
+    A: LinearOperator<T> + ?Sized,
+{
+    let diagonal = a.diagonal();
+    if diagonal.contains(&T::ZERO) {
+        return Err(MatrixError::ZeroPivotError);
+    }
+
+    let mut monitor = Monitor::new(stop, b);
+    let mut x = x0.to_vec();
+
+    while monitor.running() {
+        let ax = a.apply(&x);
+        let x_new: Vec<T> = x
+            .iter()
+            .zip(b.iter().zip(&ax))
+            .zip(&diagonal)
+            .map(|((&xi, (&bi, &axi)), &di)| xi + (bi - axi) / di)
+            .collect();
+
+        monitor.update(&x, &x_new, |v| a.apply(v));
+        x = x_new;
+    }
+
+    let ax = a.apply(&x);
+    monitor.finish(x, &ax)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stopping::StoppingTest;
+
+    // A ring of 12 intersections with a few chords across it, an exit
+    // at 1 and a well at 7.
+    fn ring_park() -> Config {
+        let n = 12;
+        let mut alleys = vec![vec![n, n + 3]];
+        alleys.extend((1..=n).map(|i| vec![i, i % n + 1, i % 4 + 1]));
+        alleys.extend([vec![2, 9, 3], vec![4, 11, 2], vec![5, 10, 6]]);
+        Config::build(crate::Sets(vec![
+            alleys,
+            vec![vec![1, 7], vec![1, 1], vec![1, 4]],
+        ]))
+    }
+
+    #[test]
+    fn test_config_operator_matches_sparse() {
+        let cfg = ring_park();
+        let (sparse, b) = Sparse::from_config(&cfg);
+        assert_eq!(cfg.right_hand_side::<f64>(), b);
+
+        let a = ParkOperator::new(&cfg);
+        let x: Vec<f64> = (0..b.len()).map(|i| (i as f64).sin()).collect();
+        assert!(crate::comparisons::compare_vecs(
+            &a.apply(&x),
+            &sparse.multiply_by_vec(&x),
+            1e-15
+        ));
+        assert!(a.diagonal().iter().all(|&d| d == 1f64));
+    }
+
+    #[test]
+    fn test_matrix_free_solvers() {
+        let cfg = ring_park();
+        let (mat, b) = Matrix::from_config(&cfg);
+        let expected = mat.gaussian_partial_pivot(&b).unwrap();
+
+        let report = cfg.solve_matrix_free(1e-12, 1_000).unwrap();
+        assert!(crate::comparisons::compare_vecs(
+            &report.solution,
+            &expected,
+            1e-10
+        ));
+
+        let stop = StoppingCriterion::new(StoppingTest::AbsoluteStep(1e-13), 10_000);
+        let x0 = vec![0f64; b.len()];
+        let report = jacobi(&ParkOperator::new(&cfg), &b, &x0, &stop).unwrap();
+        assert!(crate::comparisons::compare_vecs(
+            &report.solution,
+            &expected,
+            1e-10
+        ));
+    }
+}
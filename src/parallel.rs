@@ -0,0 +1,316 @@
+use std::sync::{Arc, Mutex};
+
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+use crate::base::*;
+use crate::matrix::Matrix;
+use crate::scalar::Scalar;
+use crate::sparse::Sparse;
+use crate::stopping::{Monitor, StoppingCriterion, StoppingTest};
+
+// Every row of a product or of a Jacobi step only reads the old vector,
+// so the rows are split across the threads without any locking. The
+// dense rows are computed exactly like the serial ones, the parallel
+// results only differ where partial sums are added in another order.
+
+/// Rows handed to a thread at once. Below this the split costs more
+/// than the rows themselves.
+pub(crate) const MIN_ROWS_PER_TASK: usize = 512;
+
+/// One thread per core, what the comparisons use for the parallel runs.
+pub fn available_threads() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Pool with `threads` workers. Pools are kept once built, so repeated
+/// products and sweeps don't spawn threads again.
+pub fn pool(threads: usize) -> Arc<ThreadPool> {
+    static POOLS: Mutex<Vec<(usize, Arc<ThreadPool>)>> = Mutex::new(Vec::new());
+
+    let threads = threads.max(1);
+    let mut pools = POOLS.lock().unwrap();
+    if let Some((_, pool)) = pools.iter().find(|(count, _)| *count == threads) {
+        return Arc::clone(pool);
+    }
+
+    let pool = Arc::new(
+        ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .expect("failed to spawn the thread pool"),
+    );
+    pools.push((threads, Arc::clone(&pool)));
+    pool
+}
+
+impl<T: Scalar> Matrix<T> {
+    pub fn par_multiply(&self, other: &Self, threads: usize) -> Result<Self, MatrixError<T>> {
+        if self.rows[0].len() != other.rows.len() {
+            return Err(MatrixError::SizeError);
+        }
+
+        let rows = pool(threads).install(|| {
+            self.rows
+                .par_iter()
+                .map(|row| {
+                    let mut out = vec![T::ZERO; other.rows[0].len()];
+                    for (&a, other_row) in row.iter().zip(&other.rows) {
+                        for (o, &b) in out.iter_mut().zip(other_row) {
+                            *o += a * b;
+                        }
+                    }
+                    out
+                })
+                .collect()
+        });
+
+        Ok(Self::from_vecs(rows))
+    }
+
+    pub fn par_multiply_by_vec(
+        &self,
+        other: &[T],
+        threads: usize,
+    ) -> Result<Vec<T>, MatrixError<T>> {
+        if self.rows[0].len() != other.len() {
+            return Err(MatrixError::SizeError);
+        }
+
+        Ok(pool(threads).install(|| {
+            self.rows
+                .par_iter()
+                .with_min_len(dense_min_len(other.len()))
+                .map(|row| Self::dot_product(row, other))
+                .collect()
+        }))
+    }
+
+    pub fn par_jacobi(
+        &self,
+        b: &[T],
+        x0: &[T],
+        threads: usize,
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        let stop = StoppingCriterion::new(StoppingTest::AbsoluteStep(eps), max_iter);
+        self.par_jacobi_until(b, x0, threads, &stop)
+    }
+
+    /// Matrix::jacobi_until with the rows of every step split across
+    /// `threads` threads. Gives the same iterates as the serial one.
+    pub fn par_jacobi_until(
+        &self,
+        b: &[T],
+        x0: &[T],
+        threads: usize,
+        stop: &StoppingCriterion,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        let diagonal: Vec<T> = (0..self.rows.len()).map(|i| self.rows[i][i]).collect();
+        if diagonal.contains(&T::ZERO) {
+            return Err(MatrixError::ZeroPivotError);
+        }
+
+        let pool = pool(threads);
+        let min_len = dense_min_len(b.len());
+        let mut monitor = Monitor::new(stop, b);
+        let mut x = x0.to_vec();
+
+        while monitor.running() {
+            let x_new = pool.install(|| {
+                (0..b.len())
+                    .into_par_iter()
+                    .with_min_len(min_len)
+                    .map(|i| x[i] + (b[i] - Self::dot_product(&self.rows[i], &x)) / diagonal[i])
+                    .collect::<Vec<T>>()
+            });
+
+            monitor.update(&x, &x_new, |v| {
+                self.par_multiply_by_vec(v, threads).unwrap()
+            });
+            x = x_new;
+        }
+
+        let ax = self.par_multiply_by_vec(&x, threads)?;
+        monitor.finish(x, &ax)
+    }
+}
+
+impl<T: Scalar> Sparse<T> {
+    /// Every thread adds its share of the entries into a vector of its
+    /// own, the vectors are summed at the end.
+    pub fn par_multiply_by_vec(&self, other: &[T], threads: usize) -> Vec<T> {
+        let n = other.len();
+        pool(threads).install(|| {
+            self.data()
+                .par_iter()
+                .fold(
+                    || vec![T::ZERO; n],
+                    |mut out, (&(i, j), &val)| {
+                        out[i] += val * other[j];
+                        out
+                    },
+                )
+                .reduce(
+                    || vec![T::ZERO; n],
+                    |mut out, part| {
+                        for (o, p) in out.iter_mut().zip(part) {
+                            *o += p;
+                        }
+                        out
+                    },
+                )
+        })
+    }
+
+    pub fn par_jacobi(
+        &self,
+        b: &[T],
+        x0: &[T],
+        threads: usize,
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        let stop = StoppingCriterion::new(StoppingTest::AbsoluteStep(eps), max_iter);
+        self.par_jacobi_until(b, x0, threads, &stop)
+    }
+
+    /// Sparse::jacobi_until on the entries grouped by row once, instead
+    /// of the whole map scanned every step, with the rows split across
+    /// `threads` threads.
+    pub fn par_jacobi_until(
+        &self,
+        b: &[T],
+        x0: &[T],
+        threads: usize,
+        stop: &StoppingCriterion,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        let rows = self.rows(b.len());
+        let diagonal: Vec<T> = rows
+            .iter()
+            .enumerate()
+            .map(|(i, row)| {
+                row.iter()
+                    .find(|&&(j, _)| j == i)
+                    .map_or(T::ZERO, |&(_, val)| val)
+            })
+            .collect();
+        if diagonal.contains(&T::ZERO) {
+            return Err(MatrixError::ZeroPivotError);
+        }
+
+        let pool = pool(threads);
+        let mul = |v: &[T]| {
+            pool.install(|| {
+                rows.par_iter()
+                    .with_min_len(MIN_ROWS_PER_TASK)
+                    .map(|row| row.iter().map(|&(j, val)| val * v[j]).sum())
+                    .collect::<Vec<T>>()
+            })
+        };
+        let mut monitor = Monitor::new(stop, b);
+        let mut x = x0.to_vec();
+
+        while monitor.running() {
+            let x_new = pool.install(|| {
+                rows.par_iter()
+                    .enumerate()
+                    .with_min_len(MIN_ROWS_PER_TASK)
+                    .map(|(i, row)| {
+                        let residual = row.iter().fold(b[i], |sum, &(j, val)| sum - val * x[j]);
+                        x[i] + residual / diagonal[i]
+                    })
+                    .collect::<Vec<T>>()
+            });
+
+            monitor.update(&x, &x_new, mul);
+            x = x_new;
+        }
+
+        let ax = mul(&x);
+        monitor.finish(x, &ax)
+    }
+}
+
+// Dense rows are long, a few of them are already worth a task.
+fn dense_min_len(row_len: usize) -> usize {
+    MIN_ROWS_PER_TASK.div_ceil(row_len.max(1)).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::grid_park;
+
+    #[test]
+    fn test_parallel_products() {
+        let cfg = grid_park(24);
+        let (mat, _) = Matrix::from_config(&cfg);
+        let (sparse, b) = Sparse::from_config(&cfg);
+        let x: Vec<f64> = (0..b.len()).map(|i| (i as f64).cos()).collect();
+
+        let serial = mat.multiply_by_vec(&x).unwrap();
+        assert_eq!(mat.par_multiply_by_vec(&x, 4).unwrap(), serial);
+        assert!(crate::comparisons::compare_vecs(
+            &sparse.par_multiply_by_vec(&x, 4),
+            &serial,
+            1e-15
+        ));
+
+        let small = Matrix::from_vecs(vec![vec![1.0, 2.0], vec![3.0, 4.0]]);
+        assert_eq!(
+            small.par_multiply(&small, 2).unwrap(),
+            small.multiply(&small).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parallel_jacobi() {
+        let cfg = grid_park(24);
+        assert!(cfg.inters.len() > MIN_ROWS_PER_TASK);
+        let (sparse, b) = Sparse::from_config(&cfg);
+        let x0 = vec![0f64; b.len()];
+
+        let serial = sparse.jacobi(&b.clone(), &x0, 1e-12, 10_000).unwrap();
+        let parallel = sparse.par_jacobi(&b, &x0, 4, 1e-12, 10_000).unwrap();
+        assert_eq!(parallel.iterations, serial.iterations);
+        assert!(crate::comparisons::compare_vecs(
+            &parallel.solution,
+            &serial.solution,
+            1e-14
+        ));
+
+        let mat = Matrix::init_default_path(40);
+        let mut b = vec![0f64; 40];
+        b[0] = 1f64;
+        let x0 = vec![0f64; 40];
+        let serial = mat.jacobi(&b, &x0, 1e-6, 100).unwrap_err();
+        let parallel = mat.par_jacobi(&b, &x0, 4, 1e-6, 100).unwrap_err();
+        match (serial, parallel) {
+            (MatrixError::NotConverged(s), MatrixError::NotConverged(p)) => {
+                assert_eq!(s.solution, p.solution)
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_parallel_jacobi_zero_diagonal() {
+        let rows = vec![vec![0.0, 1.0], vec![1.0, 2.0]];
+        let mat = Matrix::from_vecs(rows.clone());
+        let sparse = Sparse::from_vecs(rows);
+        let b = vec![1.0, 3.0];
+        let x0 = vec![0f64; 2];
+
+        let results = [
+            mat.jacobi(&b, &x0, 1e-12, 100),
+            mat.par_jacobi(&b, &x0, 4, 1e-12, 100),
+            sparse.jacobi(&b, &x0, 1e-12, 100),
+            sparse.par_jacobi(&b, &x0, 4, 1e-12, 100),
+        ];
+        for result in results {
+            assert!(matches!(result, Err(MatrixError::ZeroPivotError)));
+        }
+    }
+}
@@ -0,0 +1,301 @@
+use crate::base::*;
+use crate::matrix::Matrix;
+use crate::operator::LinearOperator;
+use crate::scalar::Scalar;
+use crate::sparse::Sparse;
+
+// https://en.wikipedia.org/wiki/Preconditioner
+
+/// Approximate inverse M⁻¹ of a system matrix A. The solvers only ever
+/// need to apply it to a residual, never M itself.
+pub trait Preconditioner<T: Scalar = f64> {
+    fn apply(&self, r: &[T]) -> Vec<T>;
+}
+
+/// M = I, the unpreconditioned solvers are the preconditioned ones with this.
+#[derive(Clone, Copy, Debug)]
+pub struct Identity;
+
+impl<T: Scalar> Preconditioner<T> for Identity {
+    fn apply(&self, r: &[T]) -> Vec<T> {
+        r.to_vec()
+    }
+}
+
+/// Diagonal scaling, M = diag(A).
+#[derive(Clone, Debug)]
+pub struct Jacobi {
+    inverse_diagonal: Vec<f64>,
+}
+
+impl Jacobi {
+    pub fn from_matrix(a: &Matrix) -> Result<Self, MatrixError> {
+        Self::from_operator(a)
+    }
+
+    pub fn from_sparse(a: &Sparse) -> Result<Self, MatrixError> {
+        Self::from_operator(a)
+    }
+
+    /// Only needs the diagonal, so it works for the matrix-free parks too.
+    pub fn from_operator<A: LinearOperator + ?Sized>(a: &A) -> Result<Self, MatrixError> {
+        let inverse_diagonal = a
+            .diagonal()
+            .into_iter()
+            .map(|val| {
+                if val != 0f64 {
+                    Ok(1f64 / val)
+                } else {
+                    Err(MatrixError::ZeroPivotError)
+                }
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { inverse_diagonal })
+    }
+}
+
+impl Preconditioner for Jacobi {
+    fn apply(&self, r: &[f64]) -> Vec<f64> {
+        r.iter()
+            .zip(&self.inverse_diagonal)
+            .map(|(ri, inv)| ri * inv)
+            .collect()
+    }
+}
+
+/// Incomplete LU factorization with no fill-in, L and U keep exactly
+/// the sparsity pattern of A.
+#[derive(Clone, Debug)]
+pub struct Ilu0 {
+    // Row i of L below the diagonal followed by row i of U, sorted by
+    // column. diagonal[i] is the position of U's diagonal entry.
+    rows: Vec<Vec<(usize, f64)>>,
+    diagonal: Vec<usize>,
+}
+
+impl Ilu0 {
+    pub fn from_matrix(a: &Matrix) -> Result<Self, MatrixError> {
+        Self::from_rows(nonzero_rows(a))
+    }
+
+    pub fn from_sparse(a: &Sparse) -> Result<Self, MatrixError> {
+        Self::from_rows(a.rows(a.size()))
+    }
+
+    // The IKJ variant of Gaussian elimination, with every update that
+    // would fall outside the pattern of row i dropped.
+    fn from_rows(mut rows: Vec<Vec<(usize, f64)>>) -> Result<Self, MatrixError> {
+        let n = rows.len();
+        let mut diagonal = Vec::with_capacity(n);
+
+        for i in 0..n {
+            let (done, rest) = rows.split_at_mut(i);
+            let row = &mut rest[0];
+
+            let mut pos = 0;
+            while pos < row.len() && row[pos].0 < i {
+                let k = row[pos].0;
+                let upper_k = &done[k][diagonal[k]..];
+                row[pos].1 /= upper_k[0].1;
+                let factor = row[pos].1;
+
+                for &(j, val) in &upper_k[1..] {
+                    if let Ok(at) = row.binary_search_by_key(&j, |&(col, _)| col) {
+                        row[at].1 -= factor * val;
+                    }
+                }
+                pos += 1;
+            }
+
+            match row.get(pos) {
+                Some(&(j, val)) if j == i && val != 0f64 => diagonal.push(pos),
+                _ => return Err(MatrixError::ZeroPivotError),
+            }
+        }
+
+        Ok(Self { rows, diagonal })
+    }
+}
+
+impl Preconditioner for Ilu0 {
+    fn apply(&self, r: &[f64]) -> Vec<f64> {
+        let n = self.rows.len();
+
+        let mut y = vec![0f64; n];
+        for i in 0..n {
+            y[i] = r[i]
+                - self.rows[i][..self.diagonal[i]]
+                    .iter()
+                    .map(|&(j, val)| val * y[j])
+                    .sum::<f64>();
+        }
+
+        let mut x = vec![0f64; n];
+        for i in (0..n).rev() {
+            let upper = &self.rows[i][self.diagonal[i]..];
+            let sum: f64 = upper[1..].iter().map(|&(j, val)| val * x[j]).sum();
+            x[i] = (y[i] - sum) / upper[0].1;
+        }
+
+        x
+    }
+}
+
+/// Incomplete Cholesky factorization with no fill-in, for the symmetric
+/// positive definite systems of from_config_symmetric and
+/// reduced_laplacian. Only the lower triangle of A is read.
+#[derive(Clone, Debug)]
+pub struct Ic0 {
+    // Row i of L, sorted by column, so the diagonal entry comes last.
+    rows: Vec<Vec<(usize, f64)>>,
+}
+
+impl Ic0 {
+    pub fn from_matrix(a: &Matrix) -> Result<Self, MatrixError> {
+        Self::from_rows(nonzero_rows(a))
+    }
+
+    pub fn from_sparse(a: &Sparse) -> Result<Self, MatrixError> {
+        Self::from_rows(a.rows(a.size()))
+    }
+
+    fn from_rows(rows: Vec<Vec<(usize, f64)>>) -> Result<Self, MatrixError> {
+        let mut lower: Vec<Vec<(usize, f64)>> = Vec::with_capacity(rows.len());
+
+        for (i, row) in rows.into_iter().enumerate() {
+            let mut row: Vec<(usize, f64)> = row.into_iter().filter(|&(j, _)| j <= i).collect();
+            if row.last().map(|&(j, _)| j) != Some(i) {
+                return Err(MatrixError::NotPositiveDefinite);
+            }
+
+            for pos in 0..row.len() {
+                let k = row[pos].0;
+                // l_ik · l_kk = a_ik - Σ l_ij · l_kj over the shared pattern.
+                let row_k: &[(usize, f64)] = if k == i { &row[..pos] } else { &lower[k] };
+                let mut sum = 0f64;
+                let (mut p, mut q) = (0, 0);
+                while p < pos && q < row_k.len() && row_k[q].0 < k {
+                    match row[p].0.cmp(&row_k[q].0) {
+                        std::cmp::Ordering::Less => p += 1,
+                        std::cmp::Ordering::Greater => q += 1,
+                        std::cmp::Ordering::Equal => {
+                            sum += row[p].1 * row_k[q].1;
+                            p += 1;
+                            q += 1;
+                        }
+                    }
+                }
+
+                let val = row[pos].1 - sum;
+                row[pos].1 = if k == i {
+                    if val <= 0f64 || val.is_nan() {
+                        return Err(MatrixError::NotPositiveDefinite);
+                    }
+                    val.sqrt()
+                } else {
+                    val / lower[k].last().unwrap().1
+                };
+            }
+
+            lower.push(row);
+        }
+
+        Ok(Self { rows: lower })
+    }
+}
+
+impl Preconditioner for Ic0 {
+    fn apply(&self, r: &[f64]) -> Vec<f64> {
+        let mut x = r.to_vec();
+
+        for (i, row) in self.rows.iter().enumerate() {
+            let (&(_, diag), below) = row.split_last().unwrap();
+            let sum: f64 = below.iter().map(|&(j, val)| val * x[j]).sum();
+            x[i] = (x[i] - sum) / diag;
+        }
+
+        // Lᵀ is upper triangular, so it is applied column by column.
+        for (i, row) in self.rows.iter().enumerate().rev() {
+            let (&(_, diag), below) = row.split_last().unwrap();
+            x[i] /= diag;
+            for &(j, val) in below {
+                x[j] -= val * x[i];
+            }
+        }
+
+        x
+    }
+}
+
+fn nonzero_rows(a: &Matrix) -> Vec<Vec<(usize, f64)>> {
+    a.rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .enumerate()
+                .filter(|(_, &val)| val != 0f64)
+                .map(|(j, &val)| (j, val))
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comparisons::compare_vecs;
+
+    #[test]
+    fn test_complete_patterns() {
+        // Without any fill-in to drop, ILU(0) and IC(0) are exact.
+        let a = Matrix::from_vecs(vec![
+            vec![4.0, -1.0, 0.0],
+            vec![-1.0, 4.0, -2.0],
+            vec![0.0, -2.0, 3.0],
+        ]);
+        let b = vec![1.0, 2.0, 3.0];
+        let expected = a.gaussian_partial_pivot(&b).unwrap();
+
+        let ilu = Ilu0::from_sparse(&Sparse::from_vecs(a.rows.clone())).unwrap();
+        assert!(compare_vecs(&expected, &ilu.apply(&b), 1e-12));
+        let ic = Ic0::from_matrix(&a).unwrap();
+        assert!(compare_vecs(&expected, &ic.apply(&b), 1e-12));
+        let jacobi = Jacobi::from_matrix(&a).unwrap();
+        assert!(compare_vecs(
+            &vec![0.25, 0.5, 1.0],
+            &jacobi.apply(&b),
+            1e-12
+        ));
+    }
+
+    #[test]
+    fn test_preconditioned_solvers() {
+        let cfg = crate::Config::build(crate::parse_config("default.config"));
+        let (a, b) = Sparse::from_config(&cfg);
+        let expected = a.gaussian_partial_pivot(&b).unwrap();
+        let x0 = vec![0f64; b.len()];
+        let ilu = Ilu0::from_sparse(&a).unwrap();
+
+        let x = a
+            .preconditioned_gmres(&b, &x0, &ilu, 10, 1e-12, 100)
+            .unwrap()
+            .solution;
+        assert!(compare_vecs(&expected, &x, 1e-10));
+        let x = a
+            .preconditioned_bicgstab(&b, &x0, &ilu, 1e-12, 100)
+            .unwrap()
+            .solution;
+        assert!(compare_vecs(&expected, &x, 1e-10));
+        let x = a.richardson(&b, &x0, &ilu, 1e-12, 100).unwrap().solution;
+        assert!(compare_vecs(&expected, &x, 1e-10));
+
+        let (a, b) = Sparse::from_config_symmetric(&cfg);
+        let ic = Ic0::from_sparse(&a).unwrap();
+        let x = a
+            .preconditioned_conjugate_gradient(&b, &x0, &ic, 1e-12, 100)
+            .unwrap()
+            .solution;
+        assert!(compare_vecs(&expected, &x, 1e-10));
+    }
+}
@@ -0,0 +1,229 @@
+use crate::base::*;
+use crate::matrix::Matrix;
+
+/// Householder QR decomposition with column pivoting, AP = QR.
+///
+/// Pivoting moves the dependent columns to the end of R, so the rank of
+/// a singular park can be read off the diagonal of R.
+#[derive(Clone, Debug)]
+pub struct QrDecomposition {
+    m: usize,
+    n: usize,
+    // Unit Householder vectors, the k-th one acts on rows k.. only.
+    reflectors: Vec<Vec<f64>>,
+    // Upper trapezoidal R, columns in pivoted order.
+    r: Vec<Vec<f64>>,
+    // perm[k] is the column of A that ended up as column k.
+    perm: Vec<usize>,
+}
+
+#[derive(Clone, Debug)]
+pub struct LeastSquaresSolution {
+    /// The minimum-norm minimizer of ‖b - Ax‖.
+    pub x: Vec<f64>,
+    pub rank: usize,
+    pub residual_norm: f64,
+}
+
+impl QrDecomposition {
+    fn factor(mut a: Vec<Vec<f64>>, pivoting: bool) -> Self {
+        let m = a.len();
+        let n = a.first().map_or(0, |row| row.len());
+        let mut perm: Vec<usize> = (0..n).collect();
+        let mut reflectors = Vec::with_capacity(m.min(n));
+
+        for k in 0..m.min(n) {
+            if pivoting {
+                let norms: Vec<f64> = (k..n)
+                    .map(|j| a[k..].iter().map(|row| row[j] * row[j]).sum())
+                    .collect();
+                let mut max_col = k;
+                for (offset, &norm) in norms.iter().enumerate() {
+                    if norm > norms[max_col - k] {
+                        max_col = k + offset;
+                    }
+                }
+                if max_col != k {
+                    for row in &mut a {
+                        row.swap(k, max_col);
+                    }
+                    perm.swap(k, max_col);
+                }
+            }
+
+            let mut v: Vec<f64> = a[k..].iter().map(|row| row[k]).collect();
+            let norm_x = norm(&v);
+            if norm_x == 0f64 {
+                reflectors.push(vec![0f64; m - k]);
+                continue;
+            }
+
+            let alpha = if v[0] >= 0f64 { -norm_x } else { norm_x };
+            v[0] -= alpha;
+            let norm_v = norm(&v);
+            for vi in &mut v {
+                *vi /= norm_v;
+            }
+
+            for j in k..n {
+                let s: f64 = 2f64
+                    * v.iter()
+                        .zip(&a[k..])
+                        .map(|(vi, row)| vi * row[j])
+                        .sum::<f64>();
+                for (vi, row) in v.iter().zip(&mut a[k..]) {
+                    row[j] -= s * vi;
+                }
+            }
+            reflectors.push(v);
+        }
+
+        for (i, row) in a.iter_mut().enumerate() {
+            for val in row.iter_mut().take(i.min(n)) {
+                *val = 0f64;
+            }
+        }
+        a.truncate(m.min(n));
+
+        Self {
+            m,
+            n,
+            reflectors,
+            r: a,
+            perm,
+        }
+    }
+
+    pub fn r(&self) -> &[Vec<f64>] {
+        &self.r
+    }
+
+    pub fn permutation(&self) -> &[usize] {
+        &self.perm
+    }
+
+    /// Number of diagonal entries of R larger than tol in absolute value.
+    pub fn rank_with_tolerance(&self, tol: f64) -> usize {
+        (0..self.r.len())
+            .take_while(|&k| self.r[k][k].abs() > tol)
+            .count()
+    }
+
+    /// Rank with the usual max(m, n) · ε · |r₀₀| tolerance.
+    pub fn rank(&self) -> usize {
+        match self.r.first() {
+            Some(row) => {
+                let tol = self.m.max(self.n) as f64 * f64::EPSILON * row[0].abs();
+                self.rank_with_tolerance(tol)
+            }
+            None => 0,
+        }
+    }
+
+    pub fn apply_qt(&self, b: &[f64]) -> Result<Vec<f64>, MatrixError> {
+        if b.len() != self.m {
+            return Err(MatrixError::SizeError);
+        }
+
+        let mut out = b.to_vec();
+        for (k, v) in self.reflectors.iter().enumerate() {
+            reflect(v, &mut out[k..]);
+        }
+        Ok(out)
+    }
+
+    /// Minimum-norm least-squares solution. The first rank rows of R are
+    /// full row rank, so a second, unpivoted QR of their transpose gives
+    /// the minimum-norm solution of R[..rank] y = (Qᵀb)[..rank].
+    pub fn solve_least_squares(&self, b: &[f64]) -> Result<LeastSquaresSolution, MatrixError> {
+        let c = self.apply_qt(b)?;
+        let rank = self.rank();
+        let residual_norm = norm(&c[rank..]);
+
+        let transposed: Vec<Vec<f64>> = (0..self.n)
+            .map(|j| self.r[..rank].iter().map(|row| row[j]).collect())
+            .collect();
+        let inner = Self::factor(transposed, false);
+
+        let mut y = vec![0f64; self.n];
+        for i in 0..rank {
+            let sum: f64 = (0..i).map(|j| inner.r[j][i] * y[j]).sum();
+            y[i] = (c[i] - sum) / inner.r[i][i];
+        }
+        for (k, v) in inner.reflectors.iter().enumerate().rev() {
+            reflect(v, &mut y[k..]);
+        }
+
+        let mut x = vec![0f64; self.n];
+        for (k, &col) in self.perm.iter().enumerate() {
+            x[col] = y[k];
+        }
+
+        Ok(LeastSquaresSolution {
+            x,
+            rank,
+            residual_norm,
+        })
+    }
+}
+
+impl Matrix {
+    // https://en.wikipedia.org/wiki/QR_decomposition#Using_Householder_reflections
+    pub fn householder_qr(&self) -> QrDecomposition {
+        QrDecomposition::factor(self.rows.clone(), true)
+    }
+
+    pub fn solve_least_squares(&self, b: &[f64]) -> Result<LeastSquaresSolution, MatrixError> {
+        self.householder_qr().solve_least_squares(b)
+    }
+
+    /// Number of diagonal entries of the pivoted R larger than tol.
+    pub fn rank(&self, tol: f64) -> usize {
+        self.householder_qr().rank_with_tolerance(tol)
+    }
+}
+
+// Applies I - 2vvᵀ to x in place.
+fn reflect(v: &[f64], x: &mut [f64]) {
+    let s = 2f64 * Matrix::dot_product(v, x);
+    for (xi, vi) in x.iter_mut().zip(v) {
+        *xi -= s * vi;
+    }
+}
+
+fn norm(v: &[f64]) -> f64 {
+    Matrix::dot_product(v, v).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comparisons::compare_vecs;
+
+    #[test]
+    fn test_least_squares_full_rank() {
+        let a = Matrix::from_vecs(vec![vec![1.0, 1.0], vec![1.0, 2.0], vec![1.0, 3.0]]);
+        let solution = a.solve_least_squares(&[1.0, 2.0, 2.0]).unwrap();
+        assert_eq!(solution.rank, 2);
+        assert!(compare_vecs(&solution.x, &vec![2.0 / 3.0, 0.5], 1e-12));
+        assert!((solution.residual_norm - (1f64 / 6f64).sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_least_squares_singular_park() {
+        // Intersections 2 and 3 only lead to each other, so the system
+        // is singular, but still consistent.
+        let a = Matrix::from_vecs(vec![
+            vec![1.0, 0.0, 0.0],
+            vec![0.0, 1.0, -1.0],
+            vec![0.0, -1.0, 1.0],
+        ]);
+        assert!(a.gaussian_partial_pivot(&vec![1.0, 0.0, 0.0]).is_err());
+
+        let solution = a.solve_least_squares(&[1.0, 0.0, 0.0]).unwrap();
+        assert_eq!(solution.rank, 2);
+        assert_eq!(a.rank(1e-12), 2);
+        assert!(compare_vecs(&solution.x, &vec![1.0, 0.0, 0.0], 1e-12));
+        assert!(solution.residual_norm < 1e-12);
+    }
+}
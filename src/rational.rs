@@ -0,0 +1,171 @@
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{ToPrimitive, Zero};
+
+use crate::base::*;
+use crate::Config;
+
+// https://en.wikipedia.org/wiki/Gaussian_elimination
+//
+// The alley lengths are integers, so every entry of the park's system is a
+// fraction and elimination over the rationals gives the probabilities
+// without any rounding. The numerators and denominators grow with every
+// step, so this is only meant for parks of a few hundred intersections.
+
+/// Dense matrix of exact fractions.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RationalMatrix {
+    pub rows: Vec<Vec<BigRational>>,
+}
+
+impl RationalMatrix {
+    /// The same system as Matrix::from_config, entry for entry, so its
+    /// solution is the exact counterpart of what the float solvers compute.
+    /// An alley of length 0 has no finite conductance, so it fails with
+    /// MatrixError::Unsolvable.
+    pub fn from_config(cfg: &Config) -> Result<(Self, Vec<BigRational>), MatrixError> {
+        if cfg.alleys.iter().any(|alley| alley.length == 0) {
+            return Err(MatrixError::Unsolvable);
+        }
+
+        let n = cfg.inters.len();
+        let mut rows = vec![vec![BigRational::zero(); n]; n];
+        let mut b = vec![BigRational::zero(); n];
+
+        for i in 0..n {
+            rows[i][i] = BigRational::from_integer(1.into());
+            if cfg.inters[i].exit {
+                b[i] = BigRational::from_integer(1.into());
+            }
+        }
+
+        let mut degrees = vec![BigRational::zero(); n];
+        for (i, _, length) in cfg.alley_ends() {
+            degrees[i] += inverse_length(length);
+        }
+
+        for (i, j, length) in cfg.alley_ends() {
+            if !cfg.inters[i].exit && !cfg.inters[i].well {
+                rows[i][j] -= inverse_length(length) / &degrees[i];
+            }
+        }
+
+        Ok((Self { rows }, b))
+    }
+
+    /// Any nonzero pivot is as good as another without rounding, so the
+    /// first one in the column is taken.
+    pub fn gaussian(&self, b: &[BigRational]) -> Result<Vec<BigRational>, MatrixError> {
+        let n = b.len();
+        if self.rows.len() != n || self.rows.iter().any(|row| row.len() != n) {
+            return Err(MatrixError::SizeError);
+        }
+
+        let mut a = self.rows.clone();
+        let mut b = b.to_vec();
+
+        for i in 0..n {
+            let pivot_row = (i..n)
+                .find(|&k| !a[k][i].is_zero())
+                .ok_or(MatrixError::Unsolvable)?;
+            a.swap(i, pivot_row);
+            b.swap(i, pivot_row);
+
+            let (upper, lower) = a.split_at_mut(i + 1);
+            let pivot = &upper[i];
+            for (k, row) in lower.iter_mut().enumerate() {
+                // Most rows of a park don't touch the pivot column at all.
+                if row[i].is_zero() {
+                    continue;
+                }
+                let factor = &row[i] / &pivot[i];
+                for j in i..n {
+                    if !pivot[j].is_zero() {
+                        row[j] -= &factor * &pivot[j];
+                    }
+                }
+                b[i + 1 + k] = &b[i + 1 + k] - &factor * &b[i];
+            }
+        }
+
+        let mut x = vec![BigRational::zero(); n];
+        for i in (0..n).rev() {
+            let mut sum = b[i].clone();
+            for j in (i + 1)..n {
+                if !a[i][j].is_zero() {
+                    sum -= &a[i][j] * &x[j];
+                }
+            }
+            x[i] = sum / &a[i][i];
+        }
+
+        Ok(x)
+    }
+}
+
+impl Config {
+    /// Escape probabilities of every intersection as exact fractions.
+    pub fn exact_solve(&self) -> Result<Vec<BigRational>, MatrixError> {
+        let (a, b) = RationalMatrix::from_config(self)?;
+        a.gaussian(&b)
+    }
+}
+
+/// Nearest floats, rounded correctly even when the numerator and the
+/// denominator don't fit in one.
+pub fn to_f64(x: &[BigRational]) -> Vec<f64> {
+    x.iter()
+        .map(|val| val.to_f64().unwrap_or(f64::NAN))
+        .collect()
+}
+
+fn inverse_length(length: usize) -> BigRational {
+    BigRational::new(1.into(), BigInt::from(length))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matrix::Matrix;
+
+    #[test]
+    fn test_exact_default_path() {
+        // From the k-th of n intersections of a path the walk escapes with
+        // probability exactly (n - 1 - k) / (n - 1).
+        let n = 7;
+        let mut alleys = vec![vec![n, n - 1]];
+        alleys.extend((1..n).map(|i| vec![i, i + 1, 3]));
+        let path = Config::build(crate::Sets(vec![
+            alleys,
+            vec![vec![1, n], vec![1, 1], vec![1, 4]],
+        ]));
+
+        let exact = path.exact_solve().unwrap();
+        for (k, val) in exact.iter().enumerate() {
+            assert_eq!(
+                *val,
+                BigRational::new(BigInt::from(n - 1 - k), BigInt::from(n - 1))
+            );
+        }
+        assert_eq!(to_f64(&exact)[2], 4.0 / 6.0);
+    }
+
+    #[test]
+    fn test_exact_matches_float() {
+        let cfg = Config::build(crate::parse_config("default.config"));
+        let exact = to_f64(&cfg.exact_solve().unwrap());
+
+        let (mat, b) = Matrix::from_config(&cfg);
+        let float = mat.gaussian_partial_pivot(&b).unwrap();
+        assert!(crate::comparisons::compare_vecs(&exact, &float, 1e-14));
+    }
+
+    #[test]
+    fn test_exact_zero_length_alley() {
+        let cfg = Config::build(crate::Sets(vec![
+            vec![vec![3, 2], vec![1, 2, 1], vec![2, 3, 0]],
+            vec![vec![1, 3], vec![1, 1], vec![1, 2]],
+        ]));
+        assert!(matches!(cfg.exact_solve(), Err(MatrixError::Unsolvable)));
+    }
+}
@@ -0,0 +1,227 @@
+use crate::base::*;
+use crate::lu::LuFactorization;
+use crate::matrix::Matrix;
+use crate::scalar::{self, Scalar};
+use crate::sparse::Sparse;
+
+// https://en.wikipedia.org/wiki/Iterative_refinement
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RefinementMode {
+    /// Factor in f32, compute residuals and corrections in f64.
+    Mixed,
+    /// Factor in f64, compute residuals with compensated dot products,
+    /// which are about as accurate as twice the working precision.
+    Compensated,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RefinementStep {
+    /// ‖d‖∞ / ‖x‖∞ for the correction d of this step, the usual estimate
+    /// of the forward error of x before the correction.
+    pub forward_error: f64,
+    /// Normwise backward error ‖b - Ax‖∞ / (‖A‖∞‖x‖∞ + ‖b‖∞).
+    pub backward_error: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct RefinementReport {
+    pub x: Vec<f64>,
+    pub steps: Vec<RefinementStep>,
+}
+
+impl Matrix {
+    pub fn refine(
+        &self,
+        b: &[f64],
+        mode: RefinementMode,
+        max_steps: usize,
+    ) -> Result<RefinementReport, MatrixError> {
+        let rows: Vec<Vec<(usize, f64)>> = self
+            .rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .enumerate()
+                    .filter(|(_, &val)| val != 0f64)
+                    .map(|(j, &val)| (j, val))
+                    .collect()
+            })
+            .collect();
+
+        match mode {
+            RefinementMode::Mixed => {
+                let single =
+                    Matrix::from_vecs(self.rows.iter().map(|row| scalar::from_f64(row)).collect());
+                let lu = LuFactorization::<f32>::from_matrix(&single).map_err(widen)?;
+                refine(&rows, b, |r| solve_single(&lu, r), false, max_steps)
+            }
+            RefinementMode::Compensated => {
+                let lu = LuFactorization::from_matrix(self)?;
+                refine(&rows, b, |r| lu.solve(r), true, max_steps)
+            }
+        }
+    }
+}
+
+impl Sparse {
+    pub fn refine(
+        &self,
+        b: &[f64],
+        mode: RefinementMode,
+        max_steps: usize,
+    ) -> Result<RefinementReport, MatrixError> {
+        let rows = self.rows(b.len());
+
+        match mode {
+            RefinementMode::Mixed => {
+                let mut single = Sparse::<f32>::from_size(rows.len());
+                for (&(i, j), &val) in self.data() {
+                    single.set_value(i, j, f32::from_f64(val));
+                }
+                let lu = LuFactorization::from_sparse(&single).map_err(widen)?;
+                refine(&rows, b, |r| solve_single(&lu, r), false, max_steps)
+            }
+            RefinementMode::Compensated => {
+                let lu = LuFactorization::from_sparse(self)?;
+                refine(&rows, b, |r| lu.solve(r), true, max_steps)
+            }
+        }
+    }
+}
+
+// Stops early once the correction drops to the rounding level of x or
+// stops shrinking, refinement can't get any further from there.
+fn refine<F>(
+    rows: &[Vec<(usize, f64)>],
+    b: &[f64],
+    solve: F,
+    compensated: bool,
+    max_steps: usize,
+) -> Result<RefinementReport, MatrixError>
+where
+    F: Fn(&[f64]) -> Result<Vec<f64>, MatrixError>,
+{
+    let a_norm = rows
+        .iter()
+        .map(|row| row.iter().map(|(_, val)| val.abs()).sum::<f64>())
+        .fold(0f64, f64::max);
+    let b_norm = norm_inf(b);
+
+    let mut x = solve(b)?;
+    let mut steps = Vec::new();
+    let mut last_correction = f64::INFINITY;
+
+    for _ in 0..max_steps {
+        let r: Vec<f64> = rows
+            .iter()
+            .zip(b)
+            .map(|(row, &bi)| {
+                if compensated {
+                    compensated_residual(row, bi, &x)
+                } else {
+                    bi - row.iter().map(|&(j, val)| val * x[j]).sum::<f64>()
+                }
+            })
+            .collect();
+        let d = solve(&r)?;
+
+        let x_norm = norm_inf(&x);
+        let correction = norm_inf(&d);
+        steps.push(RefinementStep {
+            forward_error: correction / x_norm,
+            backward_error: norm_inf(&r) / (a_norm * x_norm + b_norm),
+        });
+
+        for (xi, di) in x.iter_mut().zip(&d) {
+            *xi += di;
+        }
+
+        if correction <= f64::EPSILON * x_norm || correction > 0.5 * last_correction {
+            break;
+        }
+        last_correction = correction;
+    }
+
+    Ok(RefinementReport { x, steps })
+}
+
+// b - row · x with error-free transformations, see Ogita, Rump and Oishi,
+// "Accurate sum and dot product" (Dot2).
+fn compensated_residual(row: &[(usize, f64)], b: f64, x: &[f64]) -> f64 {
+    let mut sum = b;
+    let mut compensation = 0f64;
+
+    for &(j, val) in row {
+        let product = -val * x[j];
+        let product_error = (-val).mul_add(x[j], -product);
+        let new_sum = sum + product;
+        let z = new_sum - sum;
+        let sum_error = (sum - (new_sum - z)) + (product - z);
+        sum = new_sum;
+        compensation += product_error + sum_error;
+    }
+
+    sum + compensation
+}
+
+fn norm_inf(v: &[f64]) -> f64 {
+    v.iter().fold(0f64, |max, val| max.max(val.abs()))
+}
+
+// The f32 factors solve in f32, the refinement only sees f64.
+fn solve_single(lu: &LuFactorization<f32>, r: &[f64]) -> Result<Vec<f64>, MatrixError> {
+    let x = lu.solve(&scalar::from_f64(r)).map_err(widen)?;
+    Ok(scalar::to_f64(&x))
+}
+
+fn widen(e: MatrixError<f32>) -> MatrixError {
+    match e {
+        MatrixError::SizeError => MatrixError::SizeError,
+        MatrixError::ZeroPivotError => MatrixError::ZeroPivotError,
+        MatrixError::Unsolvable => MatrixError::Unsolvable,
+        MatrixError::NotPositiveDefinite => MatrixError::NotPositiveDefinite,
+        MatrixError::NotConverged(report) => MatrixError::NotConverged(SolveReport {
+            solution: scalar::to_f64(&report.solution),
+            iterations: report.iterations,
+            converged: report.converged,
+            residual_norm: report.residual_norm,
+            residual_history: report.residual_history,
+        }),
+        MatrixError::Divergent(rho) => MatrixError::Divergent(rho),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comparisons::compare_vecs;
+    use crate::Config;
+
+    #[test]
+    fn test_mixed_refinement() {
+        let a = Matrix::from_vecs(vec![
+            vec![4.0, 1.0, 0.0],
+            vec![1.0, 3.0, 1.0],
+            vec![0.0, 1.0, 2.0],
+        ]);
+        let expected = vec![0.1, -0.2, 1.0 / 3.0];
+        let b = a.multiply_by_vec(&expected).unwrap();
+
+        let report = a.refine(&b, RefinementMode::Mixed, 10).unwrap();
+        assert!(compare_vecs(&expected, &report.x, 1e-14));
+        assert!(report.steps[0].forward_error > 1e-9);
+        assert!(report.steps.last().unwrap().backward_error < 1e-15);
+    }
+
+    #[test]
+    fn test_sparse_compensated_refinement() {
+        let cfg = Config::build(crate::parse_config("default.config"));
+        let (a, b) = Sparse::from_config(&cfg);
+        let expected = a.gaussian_partial_pivot(&b).unwrap();
+
+        let report = a.refine(&b, RefinementMode::Compensated, 10).unwrap();
+        assert!(compare_vecs(&expected, &report.x, 1e-15));
+        assert!(report.steps.iter().all(|step| step.backward_error < 1e-15));
+    }
+}
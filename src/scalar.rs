@@ -0,0 +1,439 @@
+use std::cmp::Ordering;
+use std::fmt::{Debug, Display};
+use std::iter::{Product, Sum};
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+/// Floating-point type the matrices and their solvers compute in.
+///
+/// Stopping tolerances, residual norms and spectral radii stay f64 no
+/// matter the scalar, they only need a few correct digits.
+pub trait Scalar:
+    Copy
+    + Debug
+    + Display
+    + PartialOrd
+    + Send
+    + Sync
+    + 'static
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+    + AddAssign
+    + SubAssign
+    + MulAssign
+    + DivAssign
+    + Sum
+    + Product
+{
+    const ZERO: Self;
+    const ONE: Self;
+    /// Distance from 1 to the next larger value.
+    const EPSILON: f64;
+
+    fn from_f64(val: f64) -> Self;
+    fn to_f64(self) -> f64;
+    fn abs(self) -> Self;
+    fn sqrt(self) -> Self;
+    fn is_nan(self) -> bool;
+    fn total_cmp(&self, other: &Self) -> Ordering;
+
+    fn max(self, other: Self) -> Self {
+        if other > self {
+            other
+        } else {
+            self
+        }
+    }
+
+    /// √(a² + b²) without overflowing in the squares.
+    fn hypot(self, other: Self) -> Self {
+        let (a, b) = (self.abs(), other.abs());
+        let big = a.max(b);
+        if big == Self::ZERO {
+            return Self::ZERO;
+        }
+        let (a, b) = (a / big, b / big);
+        big * (a * a + b * b).sqrt()
+    }
+}
+
+impl Scalar for f32 {
+    const ZERO: Self = 0f32;
+    const ONE: Self = 1f32;
+    const EPSILON: f64 = f32::EPSILON as f64;
+
+    fn from_f64(val: f64) -> Self {
+        val as f32
+    }
+
+    fn to_f64(self) -> f64 {
+        f64::from(self)
+    }
+
+    fn abs(self) -> Self {
+        f32::abs(self)
+    }
+
+    fn sqrt(self) -> Self {
+        f32::sqrt(self)
+    }
+
+    fn is_nan(self) -> bool {
+        f32::is_nan(self)
+    }
+
+    fn total_cmp(&self, other: &Self) -> Ordering {
+        f32::total_cmp(self, other)
+    }
+
+    fn hypot(self, other: Self) -> Self {
+        f32::hypot(self, other)
+    }
+}
+
+impl Scalar for f64 {
+    const ZERO: Self = 0f64;
+    const ONE: Self = 1f64;
+    const EPSILON: f64 = f64::EPSILON;
+
+    fn from_f64(val: f64) -> Self {
+        val
+    }
+
+    fn to_f64(self) -> f64 {
+        self
+    }
+
+    fn abs(self) -> Self {
+        f64::abs(self)
+    }
+
+    fn sqrt(self) -> Self {
+        f64::sqrt(self)
+    }
+
+    fn is_nan(self) -> bool {
+        f64::is_nan(self)
+    }
+
+    fn total_cmp(&self, other: &Self) -> Ordering {
+        f64::total_cmp(self, other)
+    }
+
+    fn hypot(self, other: Self) -> Self {
+        f64::hypot(self, other)
+    }
+}
+
+// https://en.wikipedia.org/wiki/Quadruple-precision_floating-point_format#Double-double_arithmetic
+//
+// The unevaluated sum hi + lo of two f64 with |lo| ≤ ulp(hi) / 2, about
+// 32 significant digits with the exponent range of f64. The algorithms are
+// the ones of the QD library by Hida, Li and Bailey, built on error-free
+// transformations like the compensated dot product in refinement.rs.
+
+/// Double-double number, see the comment above.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct DoubleDouble {
+    hi: f64,
+    lo: f64,
+}
+
+impl DoubleDouble {
+    pub fn new(hi: f64, lo: f64) -> Self {
+        let (hi, lo) = two_sum(hi, lo);
+        Self { hi, lo }
+    }
+
+    pub fn hi(self) -> f64 {
+        self.hi
+    }
+
+    pub fn lo(self) -> f64 {
+        self.lo
+    }
+
+    fn mul_f64(self, other: f64) -> Self {
+        let (p, e) = two_prod(self.hi, other);
+        let (hi, lo) = quick_two_sum(p, e + self.lo * other);
+        Self { hi, lo }
+    }
+}
+
+// a + b = s + e exactly.
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let s = a + b;
+    let bb = s - a;
+    (s, (a - (s - bb)) + (b - bb))
+}
+
+// two_sum for |a| ≥ |b|.
+fn quick_two_sum(a: f64, b: f64) -> (f64, f64) {
+    let s = a + b;
+    (s, b - (s - a))
+}
+
+// a · b = p + e exactly.
+fn two_prod(a: f64, b: f64) -> (f64, f64) {
+    let p = a * b;
+    (p, a.mul_add(b, -p))
+}
+
+impl From<f64> for DoubleDouble {
+    fn from(val: f64) -> Self {
+        Self { hi: val, lo: 0f64 }
+    }
+}
+
+impl Add for DoubleDouble {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        let (s1, s2) = two_sum(self.hi, other.hi);
+        let (t1, t2) = two_sum(self.lo, other.lo);
+        let (s1, s2) = quick_two_sum(s1, s2 + t1);
+        let (hi, lo) = quick_two_sum(s1, s2 + t2);
+        Self { hi, lo }
+    }
+}
+
+impl Sub for DoubleDouble {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        self + -other
+    }
+}
+
+impl Mul for DoubleDouble {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        let (p, e) = two_prod(self.hi, other.hi);
+        let (hi, lo) = quick_two_sum(p, e + (self.hi * other.lo + self.lo * other.hi));
+        Self { hi, lo }
+    }
+}
+
+impl Div for DoubleDouble {
+    type Output = Self;
+
+    // Long division, one f64 digit at a time.
+    fn div(self, other: Self) -> Self {
+        let q1 = self.hi / other.hi;
+        let r = self - other.mul_f64(q1);
+        let q2 = r.hi / other.hi;
+        let r = r - other.mul_f64(q2);
+        let q3 = r.hi / other.hi;
+
+        let (hi, lo) = quick_two_sum(q1, q2);
+        Self { hi, lo } + Self::from(q3)
+    }
+}
+
+impl Neg for DoubleDouble {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self {
+            hi: -self.hi,
+            lo: -self.lo,
+        }
+    }
+}
+
+impl AddAssign for DoubleDouble {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for DoubleDouble {
+    fn sub_assign(&mut self, other: Self) {
+        *self = *self - other;
+    }
+}
+
+impl MulAssign for DoubleDouble {
+    fn mul_assign(&mut self, other: Self) {
+        *self = *self * other;
+    }
+}
+
+impl DivAssign for DoubleDouble {
+    fn div_assign(&mut self, other: Self) {
+        *self = *self / other;
+    }
+}
+
+impl Sum for DoubleDouble {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::ZERO, |acc, val| acc + val)
+    }
+}
+
+impl Product for DoubleDouble {
+    fn product<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::ONE, |acc, val| acc * val)
+    }
+}
+
+impl Scalar for DoubleDouble {
+    const ZERO: Self = Self { hi: 0f64, lo: 0f64 };
+    const ONE: Self = Self { hi: 1f64, lo: 0f64 };
+    // 2⁻¹⁰⁴, twice the unit roundoff of the QD library.
+    const EPSILON: f64 = 4.930380657631324e-32;
+
+    fn from_f64(val: f64) -> Self {
+        Self::from(val)
+    }
+
+    fn to_f64(self) -> f64 {
+        self.hi + self.lo
+    }
+
+    fn abs(self) -> Self {
+        if self.hi < 0f64 {
+            -self
+        } else {
+            self
+        }
+    }
+
+    // One Newton step from the f64 root doubles its correct digits.
+    fn sqrt(self) -> Self {
+        if self.hi <= 0f64 {
+            return Self::from(self.hi.sqrt());
+        }
+        let root = Self::from(self.hi.sqrt());
+        let correction = (self - root * root).hi / (2f64 * root.hi);
+        root + Self::from(correction)
+    }
+
+    fn is_nan(self) -> bool {
+        self.hi.is_nan()
+    }
+
+    fn total_cmp(&self, other: &Self) -> Ordering {
+        self.hi
+            .total_cmp(&other.hi)
+            .then(self.lo.total_cmp(&other.lo))
+    }
+}
+
+/// Scientific notation with 32 significant digits, or with the
+/// requested precision, `{:.5}` gives 6 digits like `{:.5e}` for f64.
+impl Display for DoubleDouble {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.hi.is_finite() || self.hi == 0f64 {
+            return write!(f, "{:e}", self.hi);
+        }
+
+        let digits = f.precision().map_or(32, |p| p + 1);
+        let ten = Self::from(10f64);
+        let mut exponent = self.hi.abs().log10().floor() as i32;
+        let mut power = Self::ONE;
+        for _ in 0..exponent.unsigned_abs() {
+            power *= ten;
+        }
+        let mut scaled = if exponent >= 0 {
+            self.abs() / power
+        } else {
+            self.abs() * power
+        };
+        // log10 of hi can be off by one next to a power of ten.
+        if scaled.hi >= 10f64 {
+            scaled /= ten;
+            exponent += 1;
+        } else if scaled.hi < 1f64 {
+            scaled *= ten;
+            exponent -= 1;
+        }
+
+        let mut out = String::new();
+        if self.hi < 0f64 {
+            out.push('-');
+        }
+        for i in 0..digits {
+            let mut digit = scaled.hi.floor().clamp(0f64, 9f64);
+            if (scaled - Self::from(digit)).hi < 0f64 {
+                digit -= 1f64;
+            }
+            out.push(char::from(b'0' + digit as u8));
+            if i == 0 && digits > 1 {
+                out.push('.');
+            }
+            scaled = (scaled - Self::from(digit)) * ten;
+        }
+
+        write!(f, "{}e{}", out, exponent)
+    }
+}
+
+/// Rounds every entry to f64.
+pub fn to_f64<T: Scalar>(x: &[T]) -> Vec<f64> {
+    x.iter().map(|val| val.to_f64()).collect()
+}
+
+pub fn from_f64<T: Scalar>(x: &[f64]) -> Vec<T> {
+    x.iter().map(|&val| T::from_f64(val)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::base::*;
+    use crate::matrix::Matrix;
+    use crate::sparse::Sparse;
+
+    #[test]
+    fn test_double_double() {
+        let three = DoubleDouble::from(3f64);
+        let third = DoubleDouble::ONE / three;
+        assert!(third.lo() != 0f64);
+        assert!((third * three - DoubleDouble::ONE).abs().hi() < 1e-31);
+
+        let root = DoubleDouble::from(2f64).sqrt();
+        assert!((root * root - DoubleDouble::from(2f64)).abs().hi() < 1e-31);
+        assert_eq!(format!("{}", third), "3.3333333333333333333333333333333e-1");
+        assert_eq!(format!("{:.3}", -root), "-1.414e0");
+    }
+
+    #[test]
+    fn test_precisions_on_default_path() {
+        // The answer 1 - i / (n - 1) is the same in every precision, the
+        // error of each one tracks its epsilon.
+        let n = 50;
+        let expected: Vec<f64> = (0..n).map(|i| 1.0 - i as f64 / (n - 1) as f64).collect();
+        let error = |x: Vec<f64>| {
+            x.iter()
+                .zip(&expected)
+                .map(|(xi, ei)| (xi - ei).abs())
+                .fold(0f64, f64::max)
+        };
+
+        let mut b = vec![0f32; n];
+        b[0] = 1f32;
+        let single = Matrix::<f32>::init_default_path(n)
+            .gaussian_partial_pivot(&b)
+            .unwrap();
+        let single_error = error(to_f64(&single));
+        assert!(single_error < 1e3 * f32::EPSILON as f64 && single_error > 1e-12);
+
+        let mut b = vec![DoubleDouble::ZERO; n];
+        b[0] = DoubleDouble::ONE;
+        let a = Sparse::<DoubleDouble>::init_default_path(n);
+        let double = a.gaussian_partial_pivot(&b).unwrap();
+        // The f64 answer is itself rounded, so the check runs in double-double.
+        let last = DoubleDouble::from_f64((n - 1) as f64);
+        assert!(double.iter().enumerate().all(|(i, &xi)| {
+            let ei = DoubleDouble::ONE - DoubleDouble::from_f64(i as f64) / last;
+            (xi - ei).abs().to_f64() < 1e3 * DoubleDouble::EPSILON
+        }));
+        let x0 = vec![DoubleDouble::ZERO; n];
+        let cg = a.bicgstab(&b, &x0, 1e-25, 1_000).unwrap();
+        assert!(cg.residual_norm < 1e-25);
+    }
+}
@@ -0,0 +1,306 @@
+use std::collections::VecDeque;
+
+use rayon::prelude::*;
+
+use crate::base::*;
+use crate::lu::LuFactorization;
+use crate::parallel;
+use crate::scalar::Scalar;
+use crate::sparse::Sparse;
+use crate::stopping::{Monitor, StoppingCriterion, StoppingTest};
+use crate::Config;
+
+// https://en.wikipedia.org/wiki/Additive_Schwarz_method
+//
+// Every step takes the residual r = b - Ax, solves A_k d = r on each
+// block k and adds d to x. With overlap each intersection only takes the
+// correction of the block that owns it (restricted additive Schwarz),
+// the plain sum of the overlapping corrections doesn't converge as a
+// stationary method. Without overlap this is block Jacobi.
+
+/// Intersections split into blocks that each own a part of the park,
+/// and the same blocks grown by a few layers of neighbours.
+#[derive(Clone, Debug)]
+pub struct Decomposition {
+    pub parts: Vec<Vec<usize>>,
+    pub extended: Vec<Vec<usize>>,
+}
+
+impl Config {
+    /// Grows the blocks from `blocks` seeds at once by breadth-first
+    /// search, every intersection goes to the seed that reaches it first.
+    /// Each new seed is the intersection farthest from the ones before,
+    /// so the seeds spread over the districts of the park. Parts of the
+    /// park no seed reaches get a block of their own.
+    pub fn partition(&self, blocks: usize) -> Vec<Vec<usize>> {
+        let n = self.inters.len();
+        let adjacency = self.adjacency();
+        let mut distance = vec![usize::MAX; n];
+        let mut seeds = Vec::new();
+
+        while seeds.len() < blocks.min(n) {
+            let seed = (0..n)
+                .max_by_key(|&i| (distance[i], usize::MAX - i))
+                .unwrap();
+            if distance[seed] == 0 {
+                break;
+            }
+            distance[seed] = 0;
+            let mut queue = VecDeque::from([seed]);
+            while let Some(i) = queue.pop_front() {
+                for &j in &adjacency[i] {
+                    if distance[i] + 1 < distance[j] {
+                        distance[j] = distance[i] + 1;
+                        queue.push_back(j);
+                    }
+                }
+            }
+            seeds.push(seed);
+        }
+
+        let mut owner = vec![usize::MAX; n];
+        let mut parts = vec![Vec::new(); seeds.len()];
+        let mut queue = VecDeque::new();
+        for (k, &seed) in seeds.iter().enumerate() {
+            owner[seed] = k;
+            queue.push_back(seed);
+        }
+        for root in 0..n {
+            if owner[root] == usize::MAX {
+                owner[root] = parts.len();
+                parts.push(Vec::new());
+                queue.push_back(root);
+            }
+            while let Some(i) = queue.pop_front() {
+                parts[owner[i]].push(i);
+                for &j in &adjacency[i] {
+                    if owner[j] == usize::MAX {
+                        owner[j] = owner[i];
+                        queue.push_back(j);
+                    }
+                }
+            }
+        }
+
+        for part in &mut parts {
+            part.sort_unstable();
+        }
+        parts
+    }
+
+    /// partition with every block grown by `overlap` layers of
+    /// neighbours, overlap 0 gives block Jacobi.
+    pub fn decompose(&self, blocks: usize, overlap: usize) -> Decomposition {
+        let adjacency = self.adjacency();
+        let parts = self.partition(blocks);
+        let extended = parts
+            .iter()
+            .map(|part| {
+                let mut inside = vec![false; adjacency.len()];
+                let mut block = part.clone();
+                for &i in part {
+                    inside[i] = true;
+                }
+                let mut layer = part.clone();
+                for _ in 0..overlap {
+                    let mut next = Vec::new();
+                    for &i in &layer {
+                        for &j in &adjacency[i] {
+                            if !inside[j] {
+                                inside[j] = true;
+                                next.push(j);
+                            }
+                        }
+                    }
+                    block.extend(&next);
+                    layer = next;
+                }
+                block.sort_unstable();
+                block
+            })
+            .collect();
+
+        Decomposition { parts, extended }
+    }
+}
+
+impl<T: Scalar> Sparse<T> {
+    pub fn schwarz(
+        &self,
+        b: &[T],
+        x0: &[T],
+        decomposition: &Decomposition,
+        threads: usize,
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        let stop = StoppingCriterion::new(StoppingTest::AbsoluteStep(eps), max_iter);
+        self.schwarz_until(b, x0, decomposition, threads, &stop)
+    }
+
+    /// Every block is factored once up front, each step then only
+    /// substitutes, independently of the other blocks on up to `threads`
+    /// threads.
+    pub fn schwarz_until(
+        &self,
+        b: &[T],
+        x0: &[T],
+        decomposition: &Decomposition,
+        threads: usize,
+        stop: &StoppingCriterion,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        let blocks = local_blocks(&self.rows(b.len()), decomposition)?;
+        let pool = parallel::pool(threads);
+        let mut monitor = Monitor::new(stop, b);
+        let mut x = x0.to_vec();
+
+        while monitor.running() {
+            let ax = self.multiply_by_vec(&x);
+            let r: Vec<T> = b.iter().zip(&ax).map(|(&bi, &axi)| bi - axi).collect();
+            let corrections = pool.install(|| {
+                blocks
+                    .par_iter()
+                    .map(|block| {
+                        let local: Vec<T> = block.indices.iter().map(|&i| r[i]).collect();
+                        block.lu.solve(&local)
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })?;
+
+            let prev = x.clone();
+            for (block, d) in blocks.iter().zip(corrections) {
+                for ((&i, &owned), di) in block.indices.iter().zip(&block.owned).zip(d) {
+                    if owned {
+                        x[i] += di;
+                    }
+                }
+            }
+            monitor.update(&prev, &x, |v| self.multiply_by_vec(v));
+        }
+
+        let ax = self.multiply_by_vec(&x);
+        monitor.finish(x, &ax)
+    }
+}
+
+struct LocalBlock<T: Scalar> {
+    indices: Vec<usize>,
+    owned: Vec<bool>,
+    lu: LuFactorization<T>,
+}
+
+// LU of A_k for every extended block, the rows restricted to the block's
+// own columns.
+fn local_blocks<T: Scalar>(
+    rows: &[Vec<(usize, T)>],
+    decomposition: &Decomposition,
+) -> Result<Vec<LocalBlock<T>>, MatrixError<T>> {
+    let mut position = vec![None; rows.len()];
+    decomposition
+        .parts
+        .iter()
+        .zip(&decomposition.extended)
+        .map(|(part, indices)| {
+            for (p, &i) in indices.iter().enumerate() {
+                position[i] = Some(p);
+            }
+            let mut block = Sparse::from_size(indices.len());
+            for (p, &i) in indices.iter().enumerate() {
+                for &(j, val) in &rows[i] {
+                    if let Some(q) = position[j] {
+                        block.set_value(p, q, val);
+                    }
+                }
+            }
+            for &i in indices {
+                position[i] = None;
+            }
+
+            let owned = indices
+                .iter()
+                .map(|i| part.binary_search(i).is_ok())
+                .collect();
+            Ok(LocalBlock {
+                indices: indices.clone(),
+                owned,
+                lu: LuFactorization::from_sparse(&block)?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partition() {
+        let cfg = districts();
+        let n = cfg.inters.len();
+        let parts = cfg.partition(4);
+        assert_eq!(parts.len(), 4);
+        let mut seen = vec![0; n];
+        for part in &parts {
+            for &i in part {
+                seen[i] += 1;
+            }
+        }
+        assert!(seen.iter().all(|&count| count == 1));
+
+        let decomposition = cfg.decompose(4, 1);
+        for (part, extended) in decomposition.parts.iter().zip(&decomposition.extended) {
+            assert!(part.iter().all(|i| extended.binary_search(i).is_ok()));
+        }
+    }
+
+    // Four rings of 12 intersections, each ring joined to the next one by
+    // a single long alley. The exit is in the first ring, the well in the
+    // last one.
+    fn districts() -> Config {
+        let ring = 12;
+        let mut alleys = Vec::new();
+        for d in 0..4 {
+            let first = d * ring + 1;
+            for i in 0..ring {
+                alleys.push(vec![first + i, first + (i + 1) % ring, i % 3 + 1]);
+            }
+            alleys.push(vec![first, first + ring / 2, 2]);
+            if d > 0 {
+                alleys.push(vec![first - ring / 2, first, 10]);
+            }
+        }
+        alleys.insert(0, vec![4 * ring, alleys.len()]);
+        Config::build(crate::Sets(vec![
+            alleys,
+            vec![vec![1, 4 * ring], vec![1, 1], vec![1, 20]],
+        ]))
+    }
+
+    #[test]
+    fn test_block_jacobi_and_schwarz() {
+        let cfg = districts();
+        let (sparse, b) = Sparse::from_config(&cfg);
+        let x0 = vec![0f64; b.len()];
+        let expected = sparse.gaussian_partial_pivot(&b).unwrap();
+        let jacobi = sparse.par_jacobi(&b, &x0, 1, 1e-13, 100_000).unwrap();
+
+        let block_jacobi = sparse
+            .schwarz(&b, &x0, &cfg.decompose(4, 0), 4, 1e-13, 100_000)
+            .unwrap();
+        let schwarz = sparse
+            .schwarz(&b, &x0, &cfg.decompose(4, 2), 4, 1e-13, 100_000)
+            .unwrap();
+        assert!(crate::comparisons::compare_vecs(
+            &block_jacobi.solution,
+            &expected,
+            1e-10
+        ));
+        assert!(crate::comparisons::compare_vecs(
+            &schwarz.solution,
+            &expected,
+            1e-10
+        ));
+        assert!(block_jacobi.iterations < jacobi.iterations);
+        assert!(schwarz.iterations <= block_jacobi.iterations);
+    }
+}
@@ -1,13 +1,20 @@
 use crate::base::*;
+use crate::krylov;
+use crate::lu::LuFactorization;
+use crate::operator;
+use crate::preconditioner::{Identity, Preconditioner};
+use crate::scalar::Scalar;
+use crate::spectral::{self, Dominance};
+use crate::stopping::{Monitor, StoppingCriterion, StoppingTest};
 use crate::Config;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Clone, Debug)]
-pub struct Sparse {
-    data: HashMap<(usize, usize), f64>,
+pub struct Sparse<T: Scalar = f64> {
+    data: HashMap<(usize, usize), T>,
 }
 
-impl std::fmt::Display for Sparse {
+impl<T: Scalar> std::fmt::Display for Sparse<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         for (pos, val) in self.data.iter() {
             writeln!(f, "{:?}: {}", pos, val)?
@@ -16,7 +23,7 @@ impl std::fmt::Display for Sparse {
     }
 }
 
-impl Sparse {
+impl<T: Scalar> Sparse<T> {
     pub fn new() -> Self {
         Self {
             data: HashMap::new(),
@@ -29,12 +36,12 @@ impl Sparse {
         }
     }
 
-    pub fn from_vecs(vec_matrix: Vec<Vec<f64>>) -> Self {
+    pub fn from_vecs(vec_matrix: Vec<Vec<T>>) -> Self {
         let mut sparse = Self::from_size(vec_matrix.len());
 
         for i in 0..vec_matrix.len() {
             for j in 0..vec_matrix[i].len() {
-                if vec_matrix[i][j] != 0f64 {
+                if vec_matrix[i][j] != T::ZERO {
                     sparse.data.insert((i, j), vec_matrix[i][j]);
                 }
             }
@@ -43,37 +50,79 @@ impl Sparse {
         sparse
     }
 
-    pub fn from_config(cfg: &Config) -> (Self, Vec<f64>) {
+    pub fn from_config(cfg: &Config) -> (Self, Vec<T>) {
         let n = cfg.inters.len();
+        let degrees = cfg.conductance_degrees::<T>();
         let mut out = Self::from_size(n);
-        let mut b = vec![0f64; n];
+        let mut b = vec![T::ZERO; n];
 
         for i in 0..n {
-            out.data.insert((i, i), 1f64);
+            out.data.insert((i, i), T::ONE);
             if cfg.inters[i].exit {
-                b[i] = 1f64;
+                b[i] = T::ONE;
             }
         }
 
-        for i in 0..n {
+        for (i, j, weight) in cfg.conductances::<T>() {
+            if !cfg.inters[i].exit && !cfg.inters[i].well {
+                *out.data.entry((i, j)).or_insert(T::ZERO) -= weight / degrees[i];
+            }
+        }
+
+        (out, b)
+    }
+
+    /// Sparse counterpart of Matrix::from_config_symmetric. Assembled in a
+    /// single pass over the alleys, so it stays cheap for the large parks
+    /// the multigrid solver is meant for.
+    pub fn from_config_symmetric(cfg: &Config) -> (Self, Vec<T>) {
+        let n = cfg.inters.len();
+        let mut out = Self::from_size(n);
+        let mut b = vec![T::ZERO; n];
+
+        for (i, inter) in cfg.inters.iter().enumerate() {
+            if inter.exit || inter.well {
+                out.data.insert((i, i), T::ONE);
+                if inter.exit {
+                    b[i] = T::ONE;
+                }
+            }
+        }
+
+        for (i, j, weight) in cfg.conductances::<T>() {
             if cfg.inters[i].exit || cfg.inters[i].well {
                 continue;
             }
 
-            let mut denom = 0f64;
-            for alley in &cfg.alleys {
-                if cfg.inters[i].id == alley.a.id || cfg.inters[i].id == alley.b.id {
-                    denom += 1f64 / alley.length as f64;
-                }
+            *out.data.entry((i, i)).or_insert(T::ZERO) += weight;
+            if cfg.inters[j].exit {
+                b[i] += weight;
+            } else if !cfg.inters[j].well {
+                *out.data.entry((i, j)).or_insert(T::ZERO) -= weight;
             }
+        }
+
+        (out, b)
+    }
 
-            for alley in &cfg.alleys {
-                if cfg.inters[i].id == alley.a.id {
-                    out.data
-                        .insert((i, alley.b.id - 1), -(1f64 / alley.length as f64) / denom);
-                } else if cfg.inters[i].id == alley.b.id {
-                    out.data
-                        .insert((i, alley.a.id - 1), -(1f64 / alley.length as f64) / denom);
+    /// Sparse counterpart of Matrix::reduced_laplacian.
+    pub fn reduced_laplacian(cfg: &Config) -> (Self, Vec<T>) {
+        let interior = cfg.interior();
+        let mut position = vec![None; cfg.inters.len()];
+        for (k, &i) in interior.iter().enumerate() {
+            position[i] = Some(k);
+        }
+
+        let mut out = Self::from_size(interior.len());
+        let mut b = vec![T::ZERO; interior.len()];
+
+        for (i, j, weight) in cfg.conductances::<T>() {
+            if let Some(k) = position[i] {
+                *out.data.entry((k, k)).or_insert(T::ZERO) += weight;
+                if cfg.inters[j].exit {
+                    b[k] += weight;
+                } else if let Some(l) = position[j] {
+                    *out.data.entry((k, l)).or_insert(T::ZERO) -= weight;
                 }
             }
         }
@@ -81,75 +130,206 @@ impl Sparse {
         (out, b)
     }
 
-    pub fn multiply_by_vec(&self, other: &Vec<f64>) -> Vec<f64> {
-        let mut out = vec![0f64; other.len()];
+    pub fn multiply_by_vec(&self, other: &[T]) -> Vec<T> {
+        let mut out = vec![T::ZERO; other.len()];
 
-        for i in 0..other.len() {
-            for j in 0..other.len() {
-                out[i] += self.get_value(i, j) * other[j];
-            }
+        for (pos, val) in &self.data {
+            out[pos.0] += *val * other[pos.1];
         }
 
         out
     }
 
-    pub fn get_value(&self, i: usize, j: usize) -> f64 {
-        *self.data.get(&(i, j)).unwrap_or(&0f64)
+    pub fn get_value(&self, i: usize, j: usize) -> T {
+        *self.data.get(&(i, j)).unwrap_or(&T::ZERO)
     }
-}
 
-impl MatrixBase for Sparse {
-    fn init_default_path(size: usize) -> Self {
-        let mut sparse = Self::from_size(size);
+    pub fn set_value(&mut self, i: usize, j: usize, val: T) {
+        self.data.insert((i, j), val);
+    }
 
-        sparse.data.insert((0, 0), 1f64);
-        sparse.data.insert((size - 1, size - 1), 1f64);
+    /// Size of the smallest square matrix holding every stored entry.
+    pub fn size(&self) -> usize {
+        self.data
+            .keys()
+            .map(|&(i, j)| i.max(j) + 1)
+            .max()
+            .unwrap_or(0)
+    }
 
-        for i in 1..size - 1 {
-            sparse.data.insert((i, i - 1), -0.5);
-            sparse.data.insert((i, i), 1f64);
-            sparse.data.insert((i, i + 1), -0.5);
-        }
+    pub(crate) fn data(&self) -> &HashMap<(usize, usize), T> {
+        &self.data
+    }
 
-        sparse
+    /// Bytes taken by the stored entries, without the map's own overhead.
+    pub fn entry_bytes(&self) -> usize {
+        self.data.len() * std::mem::size_of::<((usize, usize), T)>()
     }
 
-    // This function is heavily inspired by demo C++ sparse implementation
-    // written by my lecturer dr. Łukasz Kuszner.
-    fn jacobi(&self, b: &Vec<f64>, x0: &Vec<f64>, eps: f64, max_iter: usize) -> Vec<f64> {
-        let mut x = x0.clone();
+    /// Groups the stored entries by row, each row sorted by column, so
+    /// that row-wise sweeps don't have to scan the whole map per row.
+    pub fn rows(&self, n: usize) -> Vec<Vec<(usize, T)>> {
+        let mut rows = vec![Vec::new(); n];
+        for (&(i, j), &val) in &self.data {
+            rows[i].push((j, val));
+        }
+        for row in &mut rows {
+            row.sort_by_key(|&(j, _)| j);
+        }
+        rows
+    }
+}
 
-        for it in 0..max_iter {
-            let mut error = 0f64;
-            let x_temp = x.clone();
-            for i in 0..b.len() {
-                x[i] = b[i];
+// Gaussian elimination on rows kept as ordered maps, so that only the
+// entries that are actually there get touched. The pivot is picked among
+// all remaining entries for complete pivoting and among column `step`
+// relative to the largest entry of the original row for scaled pivoting.
+// Eliminated rows never move, the chosen (row, column) pairs are recorded
+// and walked backwards during back substitution instead.
+fn pivoted_elimination<T: Scalar>(
+    a: &Sparse<T>,
+    b: &[T],
+    complete: bool,
+) -> Result<Vec<T>, MatrixError<T>> {
+    let n = b.len();
+    let mut rows: Vec<BTreeMap<usize, T>> = a
+        .rows(n)
+        .into_iter()
+        .map(|row| row.into_iter().collect())
+        .collect();
+    let scales: Vec<T> = rows
+        .iter()
+        .map(|row| row.values().fold(T::ZERO, |max, val| max.max(val.abs())))
+        .collect();
+    let mut b_new = b.to_vec();
+    let mut active: Vec<usize> = (0..n).collect();
+    let mut pivots = Vec::with_capacity(n);
+
+    for step in 0..n {
+        let mut best: Option<(usize, usize)> = None;
+        let mut best_weight = T::ZERO;
+        for (pos, &r) in active.iter().enumerate() {
+            if complete {
+                for (&c, val) in &rows[r] {
+                    if val.abs() > best_weight {
+                        (best, best_weight) = (Some((pos, c)), val.abs());
+                    }
+                }
+            } else if let Some(val) = rows[r].get(&step) {
+                if val.abs() / scales[r] > best_weight {
+                    (best, best_weight) = (Some((pos, step)), val.abs() / scales[r]);
+                }
             }
+        }
 
-            for (pos, val) in &self.data {
-                if pos.0 != pos.1 {
-                    x[pos.0] -= x_temp[pos.1] * val;
+        let (pos, col) = match best {
+            Some(pivot) => pivot,
+            None => return Err(MatrixError::Unsolvable),
+        };
+        let p = active.swap_remove(pos);
+        let pivot_entries: Vec<(usize, T)> = rows[p].iter().map(|(&j, &v)| (j, v)).collect();
+        let pivot = rows[p][&col];
+
+        for &r in &active {
+            let factor = match rows[r].remove(&col) {
+                Some(val) => val / pivot,
+                None => continue,
+            };
+            for &(j, val) in &pivot_entries {
+                if j != col {
+                    *rows[r].entry(j).or_insert(T::ZERO) -= factor * val;
                 }
             }
+            let update = factor * b_new[p];
+            b_new[r] -= update;
+        }
 
-            for (pos, val) in &self.data {
-                if pos.0 == pos.1 {
-                    x[pos.0] /= val;
-                }
+        pivots.push((p, col));
+    }
 
-                error = error.max((x_temp[pos.0] - x[pos.0]).abs());
+    let mut out = vec![T::ZERO; n];
+    for &(p, col) in pivots.iter().rev() {
+        let mut sum = b_new[p];
+        for (&j, val) in &rows[p] {
+            if j != col {
+                sum -= *val * out[j];
             }
+        }
+        out[col] = sum / rows[p][&col];
+    }
 
-            if error < eps {
-                println!("sparse jacobi breaking at: {} iterations", it);
-                break;
+    Ok(out)
+}
+
+// One in-place SOR sweep over the grouped rows, backwards if reverse is set.
+pub(crate) fn sor_sweep<T: Scalar>(
+    rows: &[Vec<(usize, T)>],
+    b: &[T],
+    x: &mut [T],
+    omega: T,
+    reverse: bool,
+) {
+    let order: Box<dyn Iterator<Item = usize>> = if reverse {
+        Box::new((0..b.len()).rev())
+    } else {
+        Box::new(0..b.len())
+    };
+
+    for i in order {
+        let mut sigma = T::ZERO;
+        let mut diag = T::ZERO;
+        for &(j, val) in &rows[i] {
+            if j == i {
+                diag = val;
+            } else {
+                sigma += val * x[j];
             }
         }
 
-        x
+        x[i] = (T::ONE - omega) * x[i] + omega * (b[i] - sigma) / diag;
+    }
+}
+
+impl<T: Scalar> MatrixBase<T> for Sparse<T> {
+    fn init_default_path(size: usize) -> Self {
+        let mut sparse = Self::from_size(size);
+
+        sparse.data.insert((0, 0), T::ONE);
+        sparse.data.insert((size - 1, size - 1), T::ONE);
+
+        for i in 1..size - 1 {
+            sparse.data.insert((i, i - 1), -T::from_f64(0.5));
+            sparse.data.insert((i, i), T::ONE);
+            sparse.data.insert((i, i + 1), -T::from_f64(0.5));
+        }
+
+        sparse
     }
 
-    fn gaussian(&self, b: &Vec<f64>) -> Result<Vec<f64>, MatrixError> {
+    // This function is heavily inspired by demo C++ sparse implementation
+    // written by my lecturer dr. Łukasz Kuszner.
+    fn jacobi(
+        &self,
+        b: &Vec<T>,
+        x0: &Vec<T>,
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        let stop = StoppingCriterion::new(StoppingTest::AbsoluteStep(eps), max_iter);
+        self.jacobi_until(b, x0, &stop)
+    }
+
+    // The same iteration as the matrix-free one, see operator::jacobi.
+    fn jacobi_until(
+        &self,
+        b: &[T],
+        x0: &[T],
+        stop: &StoppingCriterion,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        operator::jacobi(self, b, x0, stop)
+    }
+
+    fn gaussian(&self, b: &Vec<T>) -> Result<Vec<T>, MatrixError<T>> {
         let mut a = self.clone();
         let mut b_new = b.clone();
 
@@ -167,22 +347,24 @@ impl MatrixBase for Sparse {
 
                 for k in i..b_new.len() {
                     match a.data.get(&(i, k)) {
-                        Some(entry) => a.data.insert((j, k), a.get_value(j, k) - factor * entry),
+                        Some(&entry) => a.data.insert((j, k), a.get_value(j, k) - factor * entry),
                         None => continue,
                     };
                 }
-                b_new[j] -= factor * b_new[i];
+                let update = factor * b_new[i];
+                b_new[j] -= update;
             }
         }
 
-        let mut out = vec![0f64; b_new.len()];
+        let mut out = vec![T::ZERO; b_new.len()];
         for i in (0..b_new.len()).rev() {
             out[i] = b_new[i];
             for j in (i + 1)..b_new.len() {
-                out[i] -= match a.data.get(&(i, j)) {
-                    Some(val) => val * out[j],
+                let update = match a.data.get(&(i, j)) {
+                    Some(&val) => val * out[j],
                     None => continue,
                 };
+                out[i] -= update;
             }
             out[i] /= a.get_value(i, i);
             if out[i].is_nan() {
@@ -193,72 +375,246 @@ impl MatrixBase for Sparse {
         Ok(out)
     }
 
-    fn partial_pivot(&self, b: &Vec<f64>) -> (Self, Vec<f64>) {
-        let mut a = self.clone();
-        let mut b_new = b.clone();
+    fn gaussian_partial_pivot(&self, b: &Vec<T>) -> Result<Vec<T>, MatrixError<T>> {
+        LuFactorization::from_sparse(self)?.solve(b)
+    }
 
-        for i in 0..b_new.len() {
-            let mut max_row = i;
-            for k in (i + 1)..b_new.len() {
-                match (a.data.get(&(k, i)), a.data.get(&(max_row, i))) {
-                    (Some(&val), Some(&max_val)) => {
-                        if val.abs() > max_val.abs() {
-                            max_row = k;
-                        }
-                    }
-                    (Some(_), None) => max_row = k,
-                    _ => {}
-                }
-            }
+    fn gaussian_complete_pivot(&self, b: &[T]) -> Result<Vec<T>, MatrixError<T>> {
+        pivoted_elimination(self, b, true)
+    }
+
+    fn gaussian_scaled_pivot(&self, b: &[T]) -> Result<Vec<T>, MatrixError<T>> {
+        pivoted_elimination(self, b, false)
+    }
 
-            if max_row != i {
-                for j in 0..b_new.len() {
-                    if let Some(&val) = a.data.get(&(i, j)) {
-                        a.data.insert((i, j), a.get_value(max_row, j));
-                        a.data.insert((max_row, j), val);
-                    } else if let Some(&max_val) = a.data.get(&(max_row, j)) {
-                        a.data.insert((i, j), max_val);
+    fn gauss_seidel(
+        &self,
+        b: &Vec<T>,
+        x0: &Vec<T>,
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        let stop = StoppingCriterion::new(StoppingTest::AbsoluteStep(eps), max_iter);
+        self.gauss_seidel_until(b, x0, &stop)
+    }
+
+    fn gauss_seidel_until(
+        &self,
+        b: &[T],
+        x0: &[T],
+        stop: &StoppingCriterion,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        let mut monitor = Monitor::new(stop, b);
+        let mut x = x0.to_vec();
+
+        while monitor.running() {
+            let mut x_new = vec![T::ZERO; b.len()];
+            for i in 0..b.len() {
+                for (pos, val) in &self.data {
+                    if pos.0 != pos.1 && pos.0 == i {
+                        let update = *val * if pos.1 < i { x_new[pos.1] } else { x[pos.1] };
+                        x_new[i] += update;
                     }
                 }
-                b_new.swap(i, max_row);
+
+                x_new[i] = (b[i] - x_new[i]) / self.get_value(i, i);
             }
+
+            monitor.update(&x, &x_new, |v| self.multiply_by_vec(v));
+            x = x_new;
         }
 
-        (a, b_new)
+        let ax = self.multiply_by_vec(&x);
+        monitor.finish(x, &ax)
     }
 
-    fn gaussian_partial_pivot(&self, b: &Vec<f64>) -> Result<Vec<f64>, MatrixError> {
-        let (a, b_new) = self.partial_pivot(b);
-        return a.gaussian(&b_new);
+    fn conjugate_gradient(
+        &self,
+        b: &[T],
+        x0: &[T],
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        self.preconditioned_conjugate_gradient(b, x0, &Identity, eps, max_iter)
     }
 
-    fn gauss_seidel(&self, b: &Vec<f64>, x0: &Vec<f64>, eps: f64, max_iter: usize) -> Vec<f64> {
-        let mut x = x0.clone();
+    fn gmres(
+        &self,
+        b: &[T],
+        x0: &[T],
+        restart: usize,
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        self.preconditioned_gmres(b, x0, &Identity, restart, eps, max_iter)
+    }
 
-        for it in 0..max_iter {
-            let mut x_new = vec![0f64; b.len()];
-            let mut error = 0f64;
+    fn bicgstab(
+        &self,
+        b: &[T],
+        x0: &[T],
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        self.preconditioned_bicgstab(b, x0, &Identity, eps, max_iter)
+    }
 
-            for i in 0..b.len() {
-                for (pos, val) in &self.data {
-                    if pos.0 != pos.1 && pos.0 == i {
-                        x_new[i] += val * if pos.1 < i { x_new[pos.1] } else { x[pos.1] };
-                    }
-                }
+    fn preconditioned_conjugate_gradient(
+        &self,
+        b: &[T],
+        x0: &[T],
+        precond: &dyn Preconditioner<T>,
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        krylov::conjugate_gradient(self, b, x0, precond, eps, max_iter)
+    }
 
-                x_new[i] = (b[i] - x_new[i]) / self.get_value(i, i);
-                error = error.max((x_new[i] - x[i]).abs());
+    fn preconditioned_gmres(
+        &self,
+        b: &[T],
+        x0: &[T],
+        precond: &dyn Preconditioner<T>,
+        restart: usize,
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        krylov::gmres(self, b, x0, precond, restart, eps, max_iter)
+    }
+
+    fn preconditioned_bicgstab(
+        &self,
+        b: &[T],
+        x0: &[T],
+        precond: &dyn Preconditioner<T>,
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        krylov::bicgstab(self, b, x0, precond, eps, max_iter)
+    }
+
+    fn richardson(
+        &self,
+        b: &[T],
+        x0: &[T],
+        precond: &dyn Preconditioner<T>,
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        krylov::richardson(self, b, x0, precond, eps, max_iter)
+    }
+
+    fn jacobi_spectral_radius(&self, max_iter: usize) -> f64 {
+        let n = self.size();
+        let apply = |v: &[T]| {
+            let mut out = vec![T::ZERO; n];
+            for (pos, val) in &self.data {
+                if pos.0 != pos.1 {
+                    out[pos.0] -= *val * v[pos.1];
+                }
             }
+            for (i, out_i) in out.iter_mut().enumerate() {
+                *out_i /= self.get_value(i, i);
+            }
+            out
+        };
+        spectral::power_radius(apply, n, max_iter)
+    }
 
-            if error < eps {
-                println!("sparse seidel breaking at: {} iterations", it);
-                break;
+    fn gauss_seidel_spectral_radius(&self, max_iter: usize) -> f64 {
+        // G = -(D + L)⁻¹U, which is one Gauss-Seidel sweep for Ax = 0.
+        let n = self.size();
+        let rows = self.rows(n);
+        let zeros = vec![T::ZERO; n];
+        let apply = |v: &[T]| {
+            let mut x = v.to_vec();
+            sor_sweep(&rows, &zeros, &mut x, T::ONE, false);
+            x
+        };
+        spectral::power_radius(apply, n, max_iter)
+    }
+
+    fn diagonal_dominance(&self) -> Dominance {
+        let n = self.size();
+        let mut diagonal = vec![0f64; n];
+        let mut off_diagonal = vec![0f64; n];
+        for (&(i, j), val) in &self.data {
+            if i == j {
+                diagonal[i] = val.to_f64();
+            } else {
+                off_diagonal[i] += val.abs().to_f64();
             }
+        }
+        spectral::dominance(diagonal.into_iter().zip(off_diagonal), T::EPSILON)
+    }
 
-            x = x_new;
+    fn sor(
+        &self,
+        b: &[T],
+        x0: &[T],
+        omega: Relaxation,
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        let stop = StoppingCriterion::new(StoppingTest::AbsoluteStep(eps), max_iter);
+        self.sor_until(b, x0, omega, &stop)
+    }
+
+    fn sor_until(
+        &self,
+        b: &[T],
+        x0: &[T],
+        omega: Relaxation,
+        stop: &StoppingCriterion,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        let omega = T::from_f64(omega.factor(self));
+        let rows = self.rows(b.len());
+        let mut monitor = Monitor::new(stop, b);
+        let mut x = x0.to_vec();
+
+        while monitor.running() {
+            let prev = x.clone();
+            sor_sweep(&rows, b, &mut x, omega, false);
+            monitor.update(&prev, &x, |v| self.multiply_by_vec(v));
+        }
+
+        let ax = self.multiply_by_vec(&x);
+        monitor.finish(x, &ax)
+    }
+
+    fn ssor(
+        &self,
+        b: &[T],
+        x0: &[T],
+        omega: Relaxation,
+        eps: f64,
+        max_iter: usize,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        let stop = StoppingCriterion::new(StoppingTest::AbsoluteStep(eps), max_iter);
+        self.ssor_until(b, x0, omega, &stop)
+    }
+
+    fn ssor_until(
+        &self,
+        b: &[T],
+        x0: &[T],
+        omega: Relaxation,
+        stop: &StoppingCriterion,
+    ) -> Result<SolveReport<T>, MatrixError<T>> {
+        let omega = T::from_f64(omega.factor(self));
+        let rows = self.rows(b.len());
+        let mut monitor = Monitor::new(stop, b);
+        let mut x = x0.to_vec();
+
+        while monitor.running() {
+            let prev = x.clone();
+            sor_sweep(&rows, b, &mut x, omega, false);
+            sor_sweep(&rows, b, &mut x, omega, true);
+            monitor.update(&prev, &x, |v| self.multiply_by_vec(v));
         }
 
-        x
+        let ax = self.multiply_by_vec(&x);
+        monitor.finish(x, &ax)
     }
 }
 
@@ -277,4 +633,111 @@ mod tests {
         let expected = vec![1.0, 2.0, 1.0];
         assert_eq!(expected, a.multiply_by_vec(&b));
     }
+
+    #[test]
+    fn test_sparse_conjugate_gradient() {
+        let cfg = Config::build(crate::parse_config("default.config"));
+        let (a, b) = Sparse::from_config(&cfg);
+        let (spd, spd_b) = Sparse::from_config_symmetric(&cfg);
+        for (&(i, j), val) in &spd.data {
+            assert_eq!(spd.get_value(j, i), *val);
+        }
+
+        let expected = a.gaussian_partial_pivot(&b).unwrap();
+        let x0 = vec![0f64; b.len()];
+        let result = spd
+            .conjugate_gradient(&spd_b, &x0, 1e-12, 100)
+            .unwrap()
+            .solution;
+        assert!(crate::comparisons::compare_vecs(&expected, &result, 1e-10));
+    }
+
+    #[test]
+    fn test_sparse_gmres_bicgstab() {
+        let cfg = Config::build(crate::parse_config("default.config"));
+        let (a, b) = Sparse::from_config(&cfg);
+        let x0 = vec![0f64; b.len()];
+        let expected = a.gaussian_partial_pivot(&b).unwrap();
+
+        let gmres = a.gmres(&b, &x0, 2, 1e-12, 100).unwrap().solution;
+        assert!(crate::comparisons::compare_vecs(&expected, &gmres, 1e-10));
+        let bicgstab = a.bicgstab(&b, &x0, 1e-12, 100).unwrap().solution;
+        assert!(crate::comparisons::compare_vecs(
+            &expected, &bicgstab, 1e-10
+        ));
+    }
+
+    #[test]
+    fn test_sparse_sor_ssor() {
+        let n = 20;
+        let a = Sparse::init_default_path(n);
+        let mut b = vec![0f64; n];
+        b[0] = 1f64;
+        let x0 = vec![0f64; n];
+        let expected: Vec<f64> = (0..n).map(|i| 1.0 - i as f64 / (n - 1) as f64).collect();
+
+        let rho = (std::f64::consts::PI / (n - 1) as f64).cos();
+        assert!((a.jacobi_spectral_radius(10_000) - rho).abs() < 1e-6);
+
+        let sor = a
+            .sor(&b, &x0, Relaxation::Auto, 1e-14, 10_000)
+            .unwrap()
+            .solution;
+        assert!(crate::comparisons::compare_vecs(&expected, &sor, 1e-10));
+        let ssor = a
+            .ssor(&b, &x0, Relaxation::Fixed(1.5), 1e-14, 10_000)
+            .unwrap()
+            .solution;
+        assert!(crate::comparisons::compare_vecs(&expected, &ssor, 1e-10));
+    }
+
+    #[test]
+    fn test_sparse_solve_report() {
+        let cfg = Config::build(crate::parse_config("default.config"));
+        let (a, b) = Sparse::from_config(&cfg);
+        let x0 = vec![0f64; b.len()];
+
+        match a.jacobi(&b, &x0, 1e-12, 1) {
+            Err(MatrixError::NotConverged(report)) => {
+                assert_eq!(report.iterations, 1);
+                assert!(!report.converged);
+                assert!(report.residual_norm > 1e-12);
+            }
+            other => panic!("expected NotConverged, got {:?}", other),
+        }
+
+        let report = a.bicgstab(&b, &x0, 1e-12, 100).unwrap();
+        let history = report.residual_history.unwrap();
+        assert_eq!(history.len(), report.iterations + 1);
+        assert!(history.last().unwrap() < &1e-12);
+        assert!(report.residual_norm < 1e-10);
+    }
+
+    #[test]
+    fn test_sparse_gaussian_partial_pivot() {
+        let a = Sparse::from_vecs(vec![
+            vec![1.0, 1.0, 0.0],
+            vec![1.0, 1.0, 1.0],
+            vec![0.0, 1.0, 1.0],
+        ]);
+        let b = vec![2.0, 3.0, 2.0];
+        assert!(a.gaussian(&b).is_err());
+        assert_eq!(a.gaussian_partial_pivot(&b).unwrap(), vec![1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_sparse_complete_and_scaled_pivot() {
+        let a = Sparse::from_vecs(vec![
+            vec![30.0, 591400.0, 0.0],
+            vec![5.291, -6.13, 1.0],
+            vec![0.0, 2.0, 1e-3],
+        ]);
+        let expected = vec![10.0, 1.0, 2.0];
+        let b = a.multiply_by_vec(&expected);
+
+        let complete = a.gaussian_complete_pivot(&b).unwrap();
+        assert!(crate::comparisons::compare_vecs(&expected, &complete, 1e-9));
+        let scaled = a.gaussian_scaled_pivot(&b).unwrap();
+        assert!(crate::comparisons::compare_vecs(&expected, &scaled, 1e-9));
+    }
 }
@@ -0,0 +1,172 @@
+use crate::base::*;
+use crate::eigen;
+use crate::scalar::{self, Scalar};
+
+/// Estimates the spectral radius of the linear map `apply` with power
+/// iteration on apply², so that a pair of dominant eigenvalues ±ρ, which
+/// every bipartite park has, does not make the estimate oscillate. The
+/// iteration itself runs in f64.
+pub fn power_radius<T: Scalar, F>(apply: F, n: usize, max_iter: usize) -> f64
+where
+    F: Fn(&[T]) -> Vec<T>,
+{
+    let squared = |v: &[f64]| scalar::to_f64(&apply(&apply(&scalar::from_f64(v))));
+    let pair = eigen::power_iteration(squared, n, 1e-12f64.max(T::EPSILON), max_iter);
+    pair.value.abs().sqrt()
+}
+
+// https://en.wikipedia.org/wiki/Successive_over-relaxation#Convergence
+//
+// Exact for consistently ordered matrices such as init_default_path,
+// a good guess for the rest.
+pub fn optimal_omega(jacobi_radius: f64) -> f64 {
+    if jacobi_radius >= 1f64 {
+        return 1f64;
+    }
+    2f64 / (1f64 + (1f64 - jacobi_radius * jacobi_radius).sqrt())
+}
+
+/// Iterations k with ρᵏ ≤ eps for an iteration that shrinks the error by a
+/// factor of ρ per step. None if ρ ≥ 1, since then it never gets there.
+pub fn iterations_for(radius: f64, eps: f64) -> Option<usize> {
+    if radius >= 1f64 {
+        return None;
+    }
+    if radius <= 0f64 || eps >= 1f64 {
+        return Some(1);
+    }
+    Some((eps.ln() / radius.ln()).ceil() as usize)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StationaryMethod {
+    Jacobi,
+    GaussSeidel,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dominance {
+    /// |a_ii| > Σ|a_ij| over j ≠ i in every row.
+    Strict,
+    /// |a_ii| ≥ Σ|a_ij| in every row. Every park matrix is at least this,
+    /// the interior rows hold with equality.
+    Weak,
+    NotDominant,
+}
+
+/// Whether Jacobi or Gauss-Seidel converges on a matrix and roughly how
+/// fast, worked out before running either of them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConvergenceEstimate {
+    pub method: StationaryMethod,
+    /// Power iteration estimate of ρ of the iteration matrix. It closes in
+    /// on the true radius from below, so a singular park shows up as a
+    /// radius just short of 1.
+    pub spectral_radius: f64,
+    pub dominance: Dominance,
+}
+
+impl ConvergenceEstimate {
+    pub fn of<T: Scalar, M: MatrixBase<T>>(
+        a: &M,
+        method: StationaryMethod,
+        max_iter: usize,
+    ) -> Self {
+        let spectral_radius = match method {
+            StationaryMethod::Jacobi => a.jacobi_spectral_radius(max_iter),
+            StationaryMethod::GaussSeidel => a.gauss_seidel_spectral_radius(max_iter),
+        };
+
+        Self {
+            method,
+            spectral_radius,
+            dominance: a.diagonal_dominance(),
+        }
+    }
+
+    /// Strict diagonal dominance is enough for both methods, otherwise it
+    /// comes down to ρ < 1.
+    pub fn converges(&self) -> bool {
+        self.dominance == Dominance::Strict || self.spectral_radius < 1f64
+    }
+
+    /// Iterations k with ρᵏ ≤ eps, i.e. until the error has shrunk by a
+    /// factor of eps. None if the method doesn't converge.
+    pub fn predicted_iterations(&self, eps: f64) -> Option<usize> {
+        if !self.converges() {
+            return None;
+        }
+        iterations_for(self.spectral_radius.min(1f64 - f64::EPSILON), eps)
+    }
+
+    /// MatrixError::Divergent unless the method converges.
+    pub fn check(self) -> Result<Self, MatrixError> {
+        if self.converges() {
+            Ok(self)
+        } else {
+            Err(MatrixError::Divergent(self.spectral_radius))
+        }
+    }
+}
+
+// Takes (a_ii, Σ|a_ij| over j ≠ i) for every row. The alley weights of an
+// interior row only add up to its diagonal up to rounding, hence the slack,
+// which has to grow with the epsilon of the scalar the rows were built in.
+pub(crate) fn dominance<I>(rows: I, epsilon: f64) -> Dominance
+where
+    I: Iterator<Item = (f64, f64)>,
+{
+    let slack = 1e-12f64.max(16f64 * epsilon);
+    let mut out = Dominance::Strict;
+    for (diag, off_diag) in rows {
+        let tol = slack * diag.abs().max(off_diag);
+        if diag.abs() < off_diag - tol {
+            return Dominance::NotDominant;
+        }
+        if diag.abs() <= off_diag + tol {
+            out = Dominance::Weak;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matrix::Matrix;
+    use crate::sparse::Sparse;
+
+    #[test]
+    fn test_default_path_estimates() {
+        let n = 20;
+        let rho = (std::f64::consts::PI / (n - 1) as f64).cos();
+
+        let a = Matrix::<f64>::init_default_path(n);
+        let jacobi = ConvergenceEstimate::of(&a, StationaryMethod::Jacobi, 10_000);
+        assert_eq!(jacobi.dominance, Dominance::Weak);
+        assert!((jacobi.spectral_radius - rho).abs() < 1e-6);
+
+        let sparse = Sparse::<f64>::init_default_path(n);
+        let seidel = ConvergenceEstimate::of(&sparse, StationaryMethod::GaussSeidel, 10_000);
+        assert!((seidel.spectral_radius - rho * rho).abs() < 1e-6);
+
+        // Gauss-Seidel is twice as fast on a consistently ordered matrix.
+        let k = seidel.check().unwrap().predicted_iterations(1e-10).unwrap();
+        let k_jacobi = jacobi.predicted_iterations(1e-10).unwrap();
+        assert!(k_jacobi.abs_diff(2 * k) <= 2);
+    }
+
+    #[test]
+    fn test_divergent_jacobi() {
+        let a = Matrix::from_vecs(vec![vec![1.0, 2.0], vec![2.0, 1.0]]);
+        let estimate = ConvergenceEstimate::of(&a, StationaryMethod::Jacobi, 1_000);
+        assert_eq!(estimate.dominance, Dominance::NotDominant);
+        assert!((estimate.spectral_radius - 2.0).abs() < 1e-9);
+        assert_eq!(estimate.predicted_iterations(1e-10), None);
+        assert!(matches!(estimate.check(), Err(MatrixError::Divergent(_))));
+
+        let b = Matrix::from_vecs(vec![vec![3.0, 2.0], vec![2.0, 3.0]]);
+        assert_eq!(b.diagonal_dominance(), Dominance::Strict);
+        assert!(ConvergenceEstimate::of(&b, StationaryMethod::GaussSeidel, 1_000).converges());
+    }
+}
@@ -0,0 +1,209 @@
+use std::time::{Duration, Instant};
+
+use crate::base::*;
+use crate::scalar::Scalar;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Norm {
+    L1,
+    L2,
+    Inf,
+}
+
+impl Norm {
+    pub fn of<T: Scalar>(self, v: &[T]) -> f64 {
+        let values = v.iter().map(|val| val.to_f64().abs());
+        match self {
+            Self::L1 => values.sum(),
+            Self::L2 => values.map(|val| val * val).sum::<f64>().sqrt(),
+            Self::Inf => max_norm(values),
+        }
+    }
+
+    /// ‖a - b‖ without allocating the difference, which is taken in the
+    /// working precision.
+    pub fn of_difference<T: Scalar>(self, a: &[T], b: &[T]) -> f64 {
+        let diffs = a.iter().zip(b).map(|(&ai, &bi)| (ai - bi).to_f64().abs());
+        match self {
+            Self::L1 => diffs.sum(),
+            Self::L2 => diffs.map(|d| d * d).sum::<f64>().sqrt(),
+            Self::Inf => max_norm(diffs),
+        }
+    }
+}
+
+// f64::max drops NaN, which would make a diverged iterate pass the step
+// tests as if it had stopped moving.
+fn max_norm(values: impl Iterator<Item = f64>) -> f64 {
+    values.fold(
+        0f64,
+        |max, val| if val > max || val.is_nan() { val } else { max },
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StoppingTest {
+    /// ‖b - Ax‖ < eps.
+    AbsoluteResidual(f64),
+    /// ‖b - Ax‖ < eps · ‖b‖.
+    RelativeResidual(f64),
+    /// ‖x_k - x_(k-1)‖ < eps. A slowly converging iteration takes tiny
+    /// steps long before it gets close, so this one can stop far too early.
+    AbsoluteStep(f64),
+    /// ‖x_k - x_(k-1)‖ < eps · ‖x_k‖.
+    RelativeStep(f64),
+}
+
+/// When a stationary solver stops: once the test passes, after max_iter
+/// iterations or once the time limit runs out, whichever comes first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StoppingCriterion {
+    pub test: StoppingTest,
+    pub norm: Norm,
+    pub max_iter: usize,
+    pub time_limit: Option<Duration>,
+}
+
+impl StoppingCriterion {
+    /// Measured in the infinity norm, with no time limit.
+    pub fn new(test: StoppingTest, max_iter: usize) -> Self {
+        Self {
+            test,
+            norm: Norm::Inf,
+            max_iter,
+            time_limit: None,
+        }
+    }
+
+    pub fn with_norm(mut self, norm: Norm) -> Self {
+        self.norm = norm;
+        self
+    }
+
+    pub fn with_time_limit(mut self, limit: Duration) -> Self {
+        self.time_limit = Some(limit);
+        self
+    }
+
+    fn tests_residual(&self) -> bool {
+        matches!(
+            self.test,
+            StoppingTest::AbsoluteResidual(_) | StoppingTest::RelativeResidual(_)
+        )
+    }
+}
+
+/// Keeps track of a running solver against its StoppingCriterion.
+pub(crate) struct Monitor<'a, T: Scalar> {
+    criterion: &'a StoppingCriterion,
+    b: &'a [T],
+    b_norm: f64,
+    start: Instant,
+    iterations: usize,
+    converged: bool,
+    history: Vec<f64>,
+}
+
+impl<'a, T: Scalar> Monitor<'a, T> {
+    pub fn new(criterion: &'a StoppingCriterion, b: &'a [T]) -> Self {
+        Self {
+            criterion,
+            b,
+            b_norm: criterion.norm.of(b),
+            start: Instant::now(),
+            iterations: 0,
+            converged: false,
+            history: Vec::new(),
+        }
+    }
+
+    pub fn running(&self) -> bool {
+        !self.converged
+            && self.iterations < self.criterion.max_iter
+            && self
+                .criterion
+                .time_limit
+                .is_none_or(|limit| self.start.elapsed() < limit)
+    }
+
+    /// Records one iteration from prev to x. mul computes Ax and is only
+    /// called by the residual tests.
+    pub fn update<F>(&mut self, prev: &[T], x: &[T], mul: F)
+    where
+        F: FnOnce(&[T]) -> Vec<T>,
+    {
+        let norm = self.criterion.norm;
+        self.iterations += 1;
+        self.converged = match self.criterion.test {
+            StoppingTest::AbsoluteResidual(eps) => self.residual(x, mul) < eps,
+            StoppingTest::RelativeResidual(eps) => self.residual(x, mul) < eps * self.b_norm,
+            StoppingTest::AbsoluteStep(eps) => norm.of_difference(x, prev) < eps,
+            StoppingTest::RelativeStep(eps) => norm.of_difference(x, prev) < eps * norm.of(x),
+        };
+    }
+
+    fn residual<F>(&mut self, x: &[T], mul: F) -> f64
+    where
+        F: FnOnce(&[T]) -> Vec<T>,
+    {
+        let r = self.criterion.norm.of_difference(self.b, &mul(x));
+        self.history.push(r);
+        r
+    }
+
+    pub fn finish(self, x: Vec<T>, ax: &[T]) -> Result<SolveReport<T>, MatrixError<T>> {
+        let report = SolveReport::new(self.b, ax, x, self.iterations, self.converged);
+        if self.criterion.tests_residual() {
+            report.with_history(self.history).into_result()
+        } else {
+            report.into_result()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comparisons::compare_vecs;
+    use crate::sparse::Sparse;
+
+    #[test]
+    fn test_step_stops_early() {
+        let n = 30;
+        let a = Sparse::init_default_path(n);
+        let mut b = vec![0f64; n];
+        b[0] = 1f64;
+        let x0 = vec![0f64; n];
+        let expected: Vec<f64> = (0..n).map(|i| 1.0 - i as f64 / (n - 1) as f64).collect();
+
+        let step = StoppingCriterion::new(StoppingTest::AbsoluteStep(1e-6), 10_000);
+        let report = a.gauss_seidel_until(&b, &x0, &step).unwrap();
+        assert!(report.residual_history.is_none());
+        assert!(!compare_vecs(&expected, &report.solution, 1e-5));
+
+        let residual = StoppingCriterion::new(StoppingTest::RelativeResidual(1e-12), 10_000)
+            .with_norm(Norm::L2);
+        let report = a.gauss_seidel_until(&b, &x0, &residual).unwrap();
+        assert!(compare_vecs(&expected, &report.solution, 1e-9));
+        let history = report.residual_history.unwrap();
+        assert_eq!(history.len(), report.iterations);
+        assert!((history[report.iterations - 1] - report.residual_norm).abs() < 1e-15);
+    }
+
+    #[test]
+    fn test_norms_and_time_limit() {
+        let v = [3.0, -4.0];
+        assert_eq!(Norm::L1.of(&v), 7.0);
+        assert_eq!(Norm::L2.of(&v), 5.0);
+        assert_eq!(Norm::Inf.of_difference(&v, &[1.0, 1.0]), 5.0);
+
+        let a = Sparse::init_default_path(10);
+        let b = vec![1f64; 10];
+        let stop = StoppingCriterion::new(StoppingTest::AbsoluteResidual(1e-12), 1_000)
+            .with_time_limit(Duration::ZERO);
+        match a.jacobi_until(&b, &b, &stop) {
+            Err(MatrixError::NotConverged(report)) => assert_eq!(report.iterations, 0),
+            other => panic!("expected NotConverged, got {:?}", other),
+        }
+    }
+}